@@ -0,0 +1,97 @@
+// Hexes of one chunk, placed and colored from the chunk's cell buffer
+//
+// Every chunk shares the same mesh of hexes around the origin. Each vertex
+// carries the index of its cell, which gives the hex centre and everything
+// the color is computed from. The color rules match `color_utils.rs`.
+
+#import bevy_sprite::mesh2d_functions::{get_world_from_local, mesh2d_position_local_to_clip}
+
+struct Cell {
+    center: vec2<f32>,
+    biome: u32,
+    secondary_weight: f32,
+    elevation: f32,
+    water_body: i32,
+    water_depth: f32,
+    shade: f32,
+    visible: u32,
+}
+
+struct ColorRange {
+    start: vec4<f32>,
+    end: vec4<f32>,
+}
+
+struct BiomeColors {
+    own: ColorRange,
+    secondary: ColorRange,
+    has_secondary: u32,
+}
+
+struct HexParams {
+    hillshade: u32,
+}
+
+@group(2) @binding(0) var<storage, read> cells: array<Cell>;
+@group(2) @binding(1) var<storage, read> biome_colors: array<BiomeColors>;
+@group(2) @binding(2) var<storage, read> water_colors: array<ColorRange>;
+@group(2) @binding(3) var<uniform> params: HexParams;
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) position: vec3<f32>,
+    @location(1) cell_index: u32,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+}
+
+fn srgb_to_linear(color: vec3<f32>) -> vec3<f32> {
+    let low = color / 12.92;
+    let high = pow((color + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(high, low, color <= vec3<f32>(0.04045));
+}
+
+// Same as `range_color`, which lerps in linear space and reads the result as sRGB
+fn range_color(range: ColorRange, t: f32) -> vec3<f32> {
+    return srgb_to_linear(mix(range.start.rgb, range.end.rgb, t));
+}
+
+fn cell_color(cell: Cell) -> vec3<f32> {
+    if cell.water_body >= 0 {
+        return range_color(water_colors[cell.water_body], cell.water_depth);
+    }
+
+    let colors = biome_colors[cell.biome];
+    var color = range_color(colors.own, cell.elevation);
+    if colors.has_secondary != 0u {
+        color = mix(color, range_color(colors.secondary, cell.elevation), cell.secondary_weight);
+    }
+    return color;
+}
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    let cell = cells[vertex.cell_index];
+
+    // Padding cells collapse onto their centre and draw nothing
+    let corner = vertex.position.xy * f32(cell.visible);
+    let local = vec4<f32>(cell.center + corner, vertex.position.z, 1.0);
+
+    var color = cell_color(cell);
+    if params.hillshade != 0u {
+        color = min(color * cell.shade, vec3<f32>(1.0));
+    }
+
+    var out: VertexOutput;
+    out.clip_position = mesh2d_position_local_to_clip(get_world_from_local(vertex.instance_index), local);
+    out.color = vec4<f32>(color, 1.0);
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
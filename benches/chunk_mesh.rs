@@ -0,0 +1,108 @@
+//! Chunk mesh build time, run with `cargo bench --bench chunk_mesh`
+//!
+//! `per_hex_builder` rebuilds the previous approach of generating every hex
+//! with `PlaneMeshBuilder` and storing normals and UVs, `hex_template` is the
+//! current `create_chunk_mesh`.
+
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_asset::RenderAssetUsages, render_resource::PrimitiveTopology},
+};
+use criterion::{Criterion, criterion_group, criterion_main};
+use hexx::{HexLayout, PlaneMeshBuilder};
+use std::hint::black_box;
+
+use bittravel::terrain::{
+    cell::Cell,
+    chunk_mesh::{HexTemplate, create_chunk_mesh},
+    color_utils::calculate_hex_color,
+    coords::{CHUNK_SIDE, HexGrid},
+    palette::BiomePalette,
+};
+
+/// A full chunk of varied cells
+fn sample_cells() -> Vec<Cell> {
+    let side_len = CHUNK_SIDE;
+    (0..side_len)
+        .flat_map(|i| (0..side_len).map(move |j| (i, j)))
+        .map(|(i, j)| Cell {
+            cell_x: side_len * 7 + i,
+            cell_z: side_len * 11 + j,
+            biome: ((i * 3 + j) % 12) as u32,
+            biome_density: 0,
+            elevation: ((i * j) % 60) as i16,
+            water_level: 10,
+            water_body_type: 1,
+        })
+        .collect()
+}
+
+/// Chunk mesh built the way it was before the hex template
+fn per_hex_mesh(cells: &[Cell], layout: &HexLayout, palette: &BiomePalette) -> Mesh {
+    let mut vertices = Vec::new();
+    let mut colors = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    let mut indices = Vec::new();
+
+    for cell in cells {
+        let world_pos = layout.hex_to_world_pos(cell.hex()).extend(0.0);
+        let mesh_info = PlaneMeshBuilder::new(layout)
+            .facing(Vec3::Z)
+            .with_scale(Vec3::splat(0.98))
+            .center_aligned()
+            .build();
+
+        let linear = calculate_hex_color(palette, cell).to_linear();
+        let first = vertices.len() as u32;
+        for vertex in mesh_info.vertices {
+            vertices.push((vertex + world_pos).to_array());
+            colors.push([linear.red, linear.green, linear.blue, linear.alpha]);
+        }
+        normals.extend(mesh_info.normals.iter().map(|n| n.to_array()));
+        uvs.extend(mesh_info.uvs.iter().map(|uv| uv.to_array()));
+        indices.extend(mesh_info.indices.iter().map(|&i| i as u32 + first));
+    }
+
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vertices);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    mesh.insert_indices(Indices::U32(indices));
+    mesh
+}
+
+fn chunk_mesh(c: &mut Criterion) {
+    let palette = BiomePalette::from_json(include_bytes!("../assets/palettes/game.palette.json"))
+        .expect("bundled palette is valid");
+    let grid = HexGrid::default();
+    let layout = grid.layout();
+    let cells = sample_cells();
+
+    let mut group = c.benchmark_group("chunk_mesh");
+    group.bench_function("per_hex_builder", |b| {
+        b.iter(|| per_hex_mesh(black_box(&cells), layout, &palette))
+    });
+    group.bench_function("hex_template", |b| {
+        // The viewer builds the template once per update, not per chunk
+        let template = HexTemplate::new(layout);
+        b.iter(|| {
+            create_chunk_mesh(
+                black_box(&cells),
+                (7, 11),
+                &template,
+                Vec2::ZERO,
+                &palette,
+                None,
+            )
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, chunk_mesh);
+criterion_main!(benches);
@@ -0,0 +1,373 @@
+use bevy::{log::Level, prelude::*};
+use std::{fmt, path::PathBuf};
+
+use crate::terrain::{instanced::Renderer, palette::THEMES, view_mode::ViewMode};
+
+pub const USAGE: &str = "\
+Usage: bittravel [OPTIONS]
+
+Options:
+  -d, --data <PATH>        Region file or directory of .bsatn files, repeatable [default: ./data]
+      --feed <LOCATION>    Stream chunks from a recorded event file, tcp://host:port
+                           or a SpacetimeDB module at ws://host:port/<module>
+      --dimension <ID>     Dimension to show first [default: detected overworld]
+      --start <X,Z>        Chunk coordinates to center the camera on, shown as soon as loaded
+      --zoom <SCALE>       Initial orthographic scale, 0.1 to 10 [default: 1]
+      --window <WxH>       Window size in pixels [default: 1000x1000]
+      --view <2d|3d>       Start with the flat map or the 3D terrain, toggle with V [default: 2d]
+      --theme <NAME>       Color theme: game, high-contrast, deuteranopia or greyscale [default: game]
+      --palette <ASSET>    Custom biome palette under assets/, shown instead of --theme
+      --hillshade          Start with relief shading on, toggle with H
+      --sun <AZ,ALT>       Sun azimuth and altitude in degrees for hillshading [default: 315,45]
+      --contours <STEP>    Start with contour lines every STEP elevation, toggle with C [default: 5]
+      --margin <CHUNKS>    Chunks loaded past each edge of the window [default: 2]
+      --mesh-cache <MB>    Memory kept for meshes of chunks that left the view [default: 256]
+      --renderer <KIND>    2D map renderer: batched or instanced [default: batched]
+      --strict             Exit with an error if any file or chunk fails to load
+      --log-config <FILE>  log4rs configuration [default: config/log4rs.yaml]
+      --log-level <LEVEL>  Replace every level in the log config, e.g. debug for bug reports
+  -h, --help               Print this help
+";
+
+/// Viewer settings taken from the command line
+#[derive(Resource, Clone, PartialEq, Debug)]
+pub struct ViewerOptions {
+    pub data_paths: Vec<PathBuf>,
+    pub feed: Option<String>,
+    pub dimension: Option<u32>,
+    pub start_chunk: Option<(i32, i32)>,
+    pub zoom: f32,
+    pub window_size: (f32, f32),
+    pub view_mode: ViewMode,
+    pub theme: String,
+    pub palette: Option<PathBuf>,
+    pub hillshade: bool,
+    /// Sun azimuth and altitude in degrees
+    pub sun: (f32, f32),
+    /// Contour interval when contours are shown from the start
+    pub contour_interval: Option<i16>,
+    /// Chunks loaded past each edge of the view
+    pub view_margin: i32,
+    /// Mesh cache budget in megabytes
+    pub mesh_cache_mb: usize,
+    pub renderer: Renderer,
+    /// Exit with an error when the load report is not complete
+    pub strict: bool,
+    pub log_config: PathBuf,
+    /// Level replacing those of the log config, also used without one
+    pub log_level: Option<Level>,
+}
+
+impl Default for ViewerOptions {
+    fn default() -> Self {
+        Self {
+            data_paths: vec![PathBuf::from("./data")],
+            feed: None,
+            dimension: None,
+            start_chunk: None,
+            zoom: 1.0,
+            window_size: (1_000.0, 1_000.0),
+            view_mode: ViewMode::Map2d,
+            theme: THEMES[0].name.to_string(),
+            palette: None,
+            hillshade: false,
+            sun: (315.0, 45.0),
+            contour_interval: None,
+            view_margin: 2,
+            mesh_cache_mb: 256,
+            renderer: Renderer::Batched,
+            strict: false,
+            log_config: PathBuf::from("config/log4rs.yaml"),
+            log_level: None,
+        }
+    }
+}
+
+/// Why the command line could not be turned into options
+#[derive(Clone, PartialEq, Debug)]
+pub enum CliError {
+    /// `--help` was requested
+    Help,
+    UnknownFlag(String),
+    MissingValue(String),
+    InvalidValue {
+        flag: String,
+        value: String,
+    },
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Help => write!(f, "help requested"),
+            CliError::UnknownFlag(flag) => write!(f, "unknown option '{flag}'"),
+            CliError::MissingValue(flag) => write!(f, "option '{flag}' needs a value"),
+            CliError::InvalidValue { flag, value } => {
+                write!(f, "invalid value '{value}' for '{flag}'")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl ViewerOptions {
+    /// Parse options from the process arguments, exiting with usage on error
+    pub fn from_env() -> Self {
+        match Self::parse(std::env::args().skip(1)) {
+            Ok(options) => options,
+            Err(CliError::Help) => {
+                print!("{USAGE}");
+                std::process::exit(0);
+            }
+            Err(e) => {
+                eprintln!("error: {e}\n\n{USAGE}");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    /// Parse options from arguments, not including the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, CliError> {
+        let mut options = Self::default();
+        let mut data_paths = Vec::new();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            // Accept both `--flag value` and `--flag=value`
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_string(), Some(value.to_string()))
+                }
+                _ => (arg, None),
+            };
+
+            if flag == "-h" || flag == "--help" {
+                return Err(CliError::Help);
+            }
+            if flag == "--hillshade" {
+                options.hillshade = true;
+                continue;
+            }
+            if flag == "--strict" {
+                options.strict = true;
+                continue;
+            }
+
+            let value = match inline_value.or_else(|| args.next()) {
+                Some(v) => v,
+                None => return Err(CliError::MissingValue(flag)),
+            };
+            let invalid = || CliError::InvalidValue {
+                flag: flag.clone(),
+                value: value.clone(),
+            };
+
+            match flag.as_str() {
+                "-d" | "--data" => data_paths.push(PathBuf::from(&value)),
+                "--feed" => options.feed = Some(value.clone()),
+                "--dimension" => options.dimension = Some(value.parse().map_err(|_| invalid())?),
+                "--start" => {
+                    options.start_chunk = Some(parse_pair(&value, ',').ok_or_else(invalid)?)
+                }
+                "--zoom" => {
+                    options.zoom = value
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|zoom| (0.1..=10.0).contains(zoom))
+                        .ok_or_else(invalid)?
+                }
+                "--window" => {
+                    let (width, height): (u32, u32) =
+                        parse_pair(&value, 'x').ok_or_else(invalid)?;
+                    options.window_size = (width as f32, height as f32);
+                }
+                "--view" => {
+                    options.view_mode = match value.as_str() {
+                        "2d" => ViewMode::Map2d,
+                        "3d" => ViewMode::Terrain3d,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "--theme" => {
+                    if !THEMES.iter().any(|theme| theme.name == value) {
+                        return Err(invalid());
+                    }
+                    options.theme = value.clone();
+                }
+                "--palette" => options.palette = Some(PathBuf::from(&value)),
+                "--contours" => {
+                    options.contour_interval = Some(
+                        value
+                            .parse::<i16>()
+                            .ok()
+                            .filter(|&interval| interval > 0)
+                            .ok_or_else(invalid)?,
+                    )
+                }
+                "--sun" => {
+                    options.sun = parse_pair(&value, ',')
+                        .filter(|&(_, altitude): &(f32, f32)| (0.0..=90.0).contains(&altitude))
+                        .ok_or_else(invalid)?
+                }
+                "--margin" => {
+                    options.view_margin = value
+                        .parse::<i32>()
+                        .ok()
+                        .filter(|&margin| margin >= 0)
+                        .ok_or_else(invalid)?
+                }
+                "--mesh-cache" => options.mesh_cache_mb = value.parse().map_err(|_| invalid())?,
+                "--renderer" => {
+                    options.renderer = match value.as_str() {
+                        "batched" => Renderer::Batched,
+                        "instanced" => Renderer::Instanced,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "--log-config" => options.log_config = PathBuf::from(&value),
+                "--log-level" => options.log_level = Some(value.parse().map_err(|_| invalid())?),
+                _ => return Err(CliError::UnknownFlag(flag.clone())),
+            }
+        }
+
+        if !data_paths.is_empty() {
+            options.data_paths = data_paths;
+        }
+
+        Ok(options)
+    }
+}
+
+/// Parse `a<sep>b` into two values
+fn parse_pair<T: std::str::FromStr>(value: &str, separator: char) -> Option<(T, T)> {
+    let (a, b) = value.split_once(separator)?;
+    Some((a.trim().parse().ok()?, b.trim().parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<ViewerOptions, CliError> {
+        ViewerOptions::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn invalid(flag: &str, value: &str) -> CliError {
+        CliError::InvalidValue {
+            flag: flag.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn no_arguments_gives_defaults() {
+        assert_eq!(parse(&[]), Ok(ViewerOptions::default()));
+    }
+
+    #[test]
+    fn data_is_repeatable_and_replaces_default() {
+        let options = parse(&["--data", "a.bsatn", "-d", "regions", "--data=b.bsatn"]).unwrap();
+        assert_eq!(
+            options.data_paths,
+            [
+                PathBuf::from("a.bsatn"),
+                PathBuf::from("regions"),
+                PathBuf::from("b.bsatn"),
+            ]
+        );
+    }
+
+    #[test]
+    fn start_accepts_negative_pairs() {
+        assert_eq!(
+            parse(&["--start", "-3,12"]).unwrap().start_chunk,
+            Some((-3, 12))
+        );
+        assert_eq!(
+            parse(&["--start=4, -5"]).unwrap().start_chunk,
+            Some((4, -5))
+        );
+        assert_eq!(parse(&["--start", "4"]), Err(invalid("--start", "4")));
+        assert_eq!(parse(&["--start", "4,x"]), Err(invalid("--start", "4,x")));
+    }
+
+    #[test]
+    fn window_takes_width_by_height() {
+        assert_eq!(
+            parse(&["--window", "1280x720"]).unwrap().window_size,
+            (1280.0, 720.0)
+        );
+        assert_eq!(
+            parse(&["--window", "1280,720"]),
+            Err(invalid("--window", "1280,720"))
+        );
+        assert_eq!(
+            parse(&["--window", "-1x720"]),
+            Err(invalid("--window", "-1x720"))
+        );
+    }
+
+    #[test]
+    fn flags_without_values() {
+        let options = parse(&["--hillshade", "--strict"]).unwrap();
+        assert!(options.hillshade);
+        assert!(options.strict);
+        assert_eq!(parse(&["--help"]), Err(CliError::Help));
+    }
+
+    #[test]
+    fn palette_and_theme() {
+        let options =
+            parse(&["--palette", "palettes/custom.json", "--theme", "greyscale"]).unwrap();
+        assert_eq!(options.palette, Some(PathBuf::from("palettes/custom.json")));
+        assert_eq!(options.theme, "greyscale");
+        assert_eq!(parse(&["--theme", "neon"]), Err(invalid("--theme", "neon")));
+    }
+
+    #[test]
+    fn log_level_overrides_only_when_given() {
+        assert_eq!(parse(&[]).unwrap().log_level, None);
+        assert_eq!(
+            parse(&["--log-level", "debug"]).unwrap().log_level,
+            Some(Level::DEBUG)
+        );
+    }
+
+    #[test]
+    fn unknown_flags_are_rejected() {
+        assert_eq!(
+            parse(&["--colour", "red"]),
+            Err(CliError::UnknownFlag("--colour".to_string()))
+        );
+    }
+
+    #[test]
+    fn missing_values_are_rejected() {
+        assert_eq!(
+            parse(&["--zoom"]),
+            Err(CliError::MissingValue("--zoom".to_string()))
+        );
+    }
+
+    #[test]
+    fn out_of_range_values_are_rejected() {
+        assert_eq!(parse(&["--zoom", "20"]), Err(invalid("--zoom", "20")));
+        assert_eq!(parse(&["--sun", "315,95"]), Err(invalid("--sun", "315,95")));
+        assert_eq!(parse(&["--contours", "0"]), Err(invalid("--contours", "0")));
+        assert_eq!(parse(&["--margin", "-1"]), Err(invalid("--margin", "-1")));
+        assert_eq!(parse(&["--view", "4d"]), Err(invalid("--view", "4d")));
+        assert_eq!(
+            parse(&["--renderer", "gpu"]),
+            Err(invalid("--renderer", "gpu"))
+        );
+        assert_eq!(
+            parse(&["--dimension", "-2"]),
+            Err(invalid("--dimension", "-2"))
+        );
+        assert_eq!(
+            parse(&["--log-level", "loud"]),
+            Err(invalid("--log-level", "loud"))
+        );
+    }
+}
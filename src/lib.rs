@@ -0,0 +1,8 @@
+//! Map viewer for BitCraft terrain chunks
+//!
+//! The binary wires these modules into a Bevy app, benchmarks use them
+//! directly.
+
+pub mod cli;
+pub mod log_targets;
+pub mod terrain;
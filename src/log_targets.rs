@@ -0,0 +1,116 @@
+//! Log targets for the viewer's subsystems
+//!
+//! Each target has its own logger in `config/log4rs.yaml`, so a subsystem's
+//! verbosity can be changed while the viewer runs, or for a whole run with
+//! `--log-level`.
+
+use log::LevelFilter;
+use log4rs::config::{Config, Deserializers, Logger, RawConfig};
+use std::{error::Error, fs, path::Path};
+
+/// Reading, indexing and validating region files and live feed rows
+pub const LOADING: &str = "bittravel::loading";
+
+/// Deciding which chunks to spawn and despawn as the camera moves
+pub const STREAMING: &str = "bittravel::streaming";
+
+/// Building meshes and visibility of spawned chunks
+pub const RENDERING: &str = "bittravel::rendering";
+
+/// Start log4rs from a YAML config, with every level in it replaced by `level`
+///
+/// Without a level the config is reloaded whenever the file changes. A given
+/// level is meant to hold for the whole run, so the file is read only once.
+pub fn init(path: &Path, level: Option<LevelFilter>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    match level {
+        Some(level) => {
+            log4rs::init_config(config_with_level(path, level)?)?;
+        }
+        None => log4rs::init_file(path, Deserializers::default())?,
+    }
+    Ok(())
+}
+
+/// Read a YAML log config with its root and every logger set to `level`
+pub fn config_with_level(
+    path: &Path,
+    level: LevelFilter,
+) -> Result<Config, Box<dyn Error + Send + Sync>> {
+    let raw: RawConfig = serde_yaml::from_str(&fs::read_to_string(path)?)?;
+
+    // Broken appenders are reported and left out, as log4rs does for init_file
+    let (appenders, mut errors) = raw.appenders_lossy(&Deserializers::default());
+    errors.handle();
+
+    let loggers = raw.loggers().into_iter().map(|logger| {
+        Logger::builder()
+            .appenders(logger.appenders().to_vec())
+            .additive(logger.additive())
+            .build(logger.name(), level)
+    });
+    let mut root = raw.root();
+    root.set_level(level);
+
+    Ok(Config::builder()
+        .appenders(appenders)
+        .loggers(loggers)
+        .build(root)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_replaces_every_configured_level() {
+        let path = std::env::temp_dir().join(format!("bittravel-log-{}.yaml", std::process::id()));
+        fs::write(
+            &path,
+            "\
+appenders:
+  stdout:
+    kind: console
+root:
+  level: warn
+  appenders:
+    - stdout
+loggers:
+  bittravel:
+    level: info
+  bittravel::rendering:
+    level: error
+    additive: false
+    appenders:
+      - stdout
+",
+        )
+        .unwrap();
+
+        let config = config_with_level(&path, LevelFilter::Debug);
+        fs::remove_file(&path).unwrap();
+        let config = config.unwrap();
+
+        assert_eq!(config.appenders().len(), 1);
+        assert_eq!(config.root().level(), LevelFilter::Debug);
+        assert_eq!(config.root().appenders(), ["stdout"]);
+
+        let mut loggers: Vec<_> = config
+            .loggers()
+            .iter()
+            .map(|logger| (logger.name(), logger.level(), logger.additive()))
+            .collect();
+        loggers.sort();
+        assert_eq!(
+            loggers,
+            [
+                ("bittravel", LevelFilter::Debug, true),
+                ("bittravel::rendering", LevelFilter::Debug, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_config_is_an_error() {
+        assert!(config_with_level(Path::new("no/such/log4rs.yaml"), LevelFilter::Info).is_err());
+    }
+}
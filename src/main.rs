@@ -1,116 +1,203 @@
 use bevy::{
+    asset::AssetPlugin,
     input::mouse::{MouseMotion, MouseWheel},
+    log::{Level, LogPlugin},
     prelude::*,
+    sprite::Material2dPlugin,
 };
 
-pub mod terrain;
-
-use terrain::{
-    chunk::TerrainChunkState,
-    dynamic_chunks::{SpawnedChunks, update_dynamic_chunks},
-    world_data::WorldData,
+use bittravel::{
+    cli::ViewerOptions,
+    log_targets::{self, LOADING},
+    terrain::{
+        contours::{Contours, toggle_contours},
+        dimension_switch::{switch_dimension, update_dimension_title},
+        dynamic_chunks::{SpawnedChunks, update_dynamic_chunks},
+        hillshade::{Hillshade, toggle_hillshade},
+        instanced::{HexInstanceMaterial, HexInstancing, Renderer, update_hex_instancing},
+        live_feed::{FeedSource, TerrainFeed, apply_terrain_feed},
+        loading::{
+            AppState, RegionLoading, poll_region_loading, start_region_loading,
+            update_loading_screen,
+        },
+        mesh_cache::MeshCache,
+        mesh_tasks::{ChunkMaterials, finish_chunk_meshes},
+        palette::{
+            BiomePalette, PaletteLoader, load_palette, refresh_on_palette_change, switch_theme,
+        },
+        view_mode::{ViewMode, setup_orbit_camera, toggle_view_mode, update_orbit_camera},
+        world_config::WorldConfig,
+        world_data::WorldData,
+    },
 };
 
-pub fn main() {
-    // og4rs::init_file("config/log4rs.yaml", Default::default()).unwrap();
-
-    App::new()
-        .add_plugins(
-            DefaultPlugins
-                .set(WindowPlugin {
-                    primary_window: Some(Window {
-                        resolution: (1_000.0, 1_000.0).into(),
-                        ..default()
-                    }),
-                    ..default()
-                })
-                .set(bevy::log::LogPlugin { ..default() }),
-        )
+pub fn main() -> AppExit {
+    let options = ViewerOptions::from_env();
+
+    // log4rs replaces Bevy's logger, so only fall back to it without a config
+    let level = options.log_level.unwrap_or(Level::INFO);
+    let level_override = options.log_level.map(level_filter);
+    let log4rs_active = match log_targets::init(&options.log_config, level_override) {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!(
+                "Could not load log config {}: {e}",
+                options.log_config.display()
+            );
+            false
+        }
+    };
+
+    let default_plugins = DefaultPlugins
+        .set(WindowPlugin {
+            primary_window: Some(Window {
+                resolution: options.window_size.into(),
+                ..default()
+            }),
+            ..default()
+        })
+        .set(AssetPlugin {
+            // Palettes are edited while the viewer runs
+            watch_for_changes_override: Some(true),
+            ..default()
+        })
+        .set(LogPlugin { level, ..default() });
+    let default_plugins = if log4rs_active {
+        default_plugins.disable::<LogPlugin>()
+    } else {
+        default_plugins
+    };
+
+    let mut app = App::new();
+    // Added first so feed errors below reach Bevy's logger without log4rs
+    app.add_plugins(default_plugins);
+
+    // Stream terrain rows from a live feed instead of reading region files
+    if let Some(location) = &options.feed {
+        match TerrainFeed::connect(FeedSource::parse(location)) {
+            Ok(feed) => {
+                app.insert_resource(feed);
+            }
+            Err(e) => {
+                log::error!(target: LOADING, "Could not connect to terrain feed {location}: {e}");
+                return AppExit::from_code(1);
+            }
+        }
+    }
+
+    let exit = app
+        .add_plugins(Material2dPlugin::<HexInstanceMaterial>::default())
+        .insert_resource(Hillshade::from_options(&options))
+        .insert_resource(Contours::from_options(&options))
+        .insert_resource(MeshCache::from_options(&options))
+        .insert_resource(WorldConfig::from_options(&options))
+        .insert_resource(options.view_mode)
+        .insert_resource(options.renderer)
+        .insert_resource(options)
+        .init_state::<AppState>()
         .init_resource::<WorldData>()
         .init_resource::<SpawnedChunks>()
-        .add_systems(Startup, (load_world_data, setup_camera).chain())
-        .add_systems(Update, (camera_controls, update_dynamic_chunks))
+        .init_resource::<ChunkMaterials>()
+        .init_resource::<HexInstancing>()
+        .init_asset::<BiomePalette>()
+        .init_asset_loader::<PaletteLoader>()
+        .add_systems(
+            Startup,
+            (
+                setup_camera,
+                setup_orbit_camera,
+                load_palette,
+                start_region_loading,
+            )
+                .chain(),
+        )
+        // Regions keep loading after the map is shown
+        .add_systems(
+            Update,
+            (poll_region_loading, update_loading_screen)
+                .chain()
+                .run_if(resource_exists::<RegionLoading>),
+        )
+        .add_systems(OnExit(AppState::Loading), center_camera)
+        .add_systems(
+            Update,
+            (
+                camera_controls,
+                switch_dimension,
+                apply_terrain_feed.run_if(resource_exists::<TerrainFeed>),
+                switch_theme,
+                toggle_hillshade,
+                toggle_contours,
+                toggle_view_mode,
+                refresh_on_palette_change,
+                update_dynamic_chunks,
+                finish_chunk_meshes,
+                update_hex_instancing.run_if(resource_equals(Renderer::Instanced)),
+                update_dimension_title,
+                update_orbit_camera.run_if(resource_equals(ViewMode::Terrain3d)),
+            )
+                .chain()
+                .run_if(in_state(AppState::Viewing)),
+        )
         .run();
 
     log::info!("Done.");
+    exit
 }
 
-/// 2D camera setup - position camera at world center
-fn setup_camera(mut commands: Commands, world_data: Res<WorldData>) {
-    // Position camera at world center so we can see terrain immediately
-    let camera_pos = Vec3::new(
-        -world_data.center_offset.x,
-        -world_data.center_offset.y,
-        0.0,
-    );
-
-    commands.spawn((Camera2d, Transform::from_translation(camera_pos)));
-
-    log::info!(
-        "Camera positioned at ({:.1}, {:.1}) to center on world",
-        camera_pos.x,
-        camera_pos.y
-    );
+/// 2D camera setup, also renders the loading screen
+/// log4rs level matching a level given for Bevy's logger
+fn level_filter(level: Level) -> log::LevelFilter {
+    match level {
+        Level::ERROR => log::LevelFilter::Error,
+        Level::WARN => log::LevelFilter::Warn,
+        Level::INFO => log::LevelFilter::Info,
+        Level::DEBUG => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    }
 }
 
-/// Load all world data into memory for dynamic chunk loading
-fn load_world_data(mut world_data: ResMut<WorldData>) {
-    let regions = TerrainChunkState::from_dir("./data").expect("Could not read data dir!");
-
-    for region in regions {
-        log::info!("Loaded {} chunks from region file", region.len());
-
-        // Filter out invalid chunks
-        let region_len = region.len();
-        let valid_chunks: Vec<_> = region
-            .into_iter()
-            .enumerate()
-            .filter_map(|(idx, chunk)| {
-                if chunk.chunk_x == 0 && chunk.chunk_z == 0 {
-                    if idx == 0 {
-                        log::info!("Including valid origin chunk at index {idx}");
-                        Some(chunk)
-                    } else {
-                        None // Filter out invalid instanced chunks at (0,0)
-                    }
-                } else {
-                    Some(chunk)
-                }
-            })
-            .collect();
+fn setup_camera(mut commands: Commands, options: Res<ViewerOptions>) {
+    commands.spawn((
+        Camera2d,
+        Camera {
+            is_active: options.view_mode == ViewMode::Map2d,
+            ..default()
+        },
+        Projection::Orthographic(OrthographicProjection {
+            scale: options.zoom,
+            ..OrthographicProjection::default_2d()
+        }),
+        Transform::IDENTITY,
+    ));
+}
 
-        log::info!(
-            "After filtering: {} valid chunks (removed {} invalid chunks)",
-            valid_chunks.len(),
-            region_len - valid_chunks.len()
-        );
+/// Position camera at the requested chunk, or the world center so we can see terrain immediately
+fn center_camera(
+    options: Res<ViewerOptions>,
+    world_data: Res<WorldData>,
+    world_config: Res<WorldConfig>,
+    mut camera_query: Query<&mut Transform, With<Camera2d>>,
+) {
+    let camera_home = world_data
+        .active()
+        .map(|dimension| match options.start_chunk {
+            Some((chunk_x, chunk_z)) => {
+                dimension.chunk_camera_position(&world_config.grid, chunk_x, chunk_z)
+            }
+            None => dimension.camera_home(),
+        })
+        .unwrap_or_default();
+    let camera_pos = camera_home.extend(0.0);
 
-        // Add region to world data
-        world_data.add_region(valid_chunks);
+    for mut transform in camera_query.iter_mut() {
+        transform.translation = camera_pos;
     }
 
-    // Calculate center offset after all regions are loaded
-    world_data.finalize();
-
-    // Log coordinate ranges for debugging
-    let chunk_coords: Vec<_> = world_data.chunks.keys().collect();
-    let min_x = chunk_coords.iter().map(|(x, _)| *x).min().unwrap_or(0);
-    let max_x = chunk_coords.iter().map(|(x, _)| *x).max().unwrap_or(0);
-    let min_z = chunk_coords.iter().map(|(_, z)| *z).min().unwrap_or(0);
-    let max_z = chunk_coords.iter().map(|(_, z)| *z).max().unwrap_or(0);
-
-    log::info!(
-        "World data loaded: {} total chunks, center offset: ({:.1}, {:.1})",
-        world_data.chunks.len(),
-        world_data.center_offset.x,
-        world_data.center_offset.y
-    );
     log::info!(
-        "Chunk coordinate ranges: X=[{}, {}], Z=[{}, {}]",
-        min_x,
-        max_x,
-        min_z,
-        max_z
+        "Camera positioned at ({:.1}, {:.1}) to center on world",
+        camera_pos.x,
+        camera_pos.y
     );
 }
 
@@ -1,4 +1,7 @@
-#[derive(Clone, PartialEq, Debug, Copy, Eq, Hash)]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Copy, Eq, Hash)]
 pub enum Biome {
     Dev,
     CalmForest,
@@ -16,3 +19,173 @@ pub enum Biome {
     Jungle,
     Sapwoods,
 }
+
+impl Biome {
+    /// All biomes, indexed by their id in game data
+    pub const ALL: [Biome; 15] = [
+        Biome::Dev,
+        Biome::CalmForest,
+        Biome::PineWoods,
+        Biome::SnowyPeaks,
+        Biome::BreezyPlains,
+        Biome::AutumnForest,
+        Biome::Tundra,
+        Biome::Desert,
+        Biome::Swamp,
+        Biome::Canyon,
+        Biome::Ocean,
+        Biome::SafeMeadows,
+        Biome::Cave,
+        Biome::Jungle,
+        Biome::Sapwoods,
+    ];
+
+    /// Id of the biome in game data
+    pub fn id(self) -> u32 {
+        self as u32
+    }
+}
+
+/// A biome id that does not name any known biome
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct UnknownBiome(pub u32);
+
+impl fmt::Display for UnknownBiome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown biome id {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownBiome {}
+
+impl TryFrom<u32> for Biome {
+    type Error = UnknownBiome;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        Biome::ALL
+            .get(value as usize)
+            .copied()
+            .ok_or(UnknownBiome(value))
+    }
+}
+
+/// A cell's packed biome value split into its parts
+///
+/// Cell biomes are stored as one `u32`: the low byte is the cell's own biome,
+/// the second byte the neighbouring biome it borders, the third byte how
+/// strongly the border is blended and the high byte holds flags, kept as raw
+/// bits since none of their meanings are known. A second byte of 0 means no
+/// border, so a border with `Biome::Dev` (id 0) cannot be represented.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct BiomeInfo {
+    pub primary: Biome,
+    pub secondary: Option<Biome>,
+    pub blend: u8,
+    pub flags: u8,
+}
+
+impl BiomeInfo {
+    /// Whether the cell lies on a border with another biome
+    pub fn is_border(&self) -> bool {
+        self.secondary.is_some()
+    }
+
+    /// Whether the cell has a blend strength set
+    pub fn is_blended(&self) -> bool {
+        self.blend != 0
+    }
+}
+
+impl TryFrom<u32> for BiomeInfo {
+    type Error = UnknownBiome;
+
+    fn try_from(packed: u32) -> Result<Self, Self::Error> {
+        let [primary, secondary, blend, flags] = packed.to_le_bytes();
+
+        Ok(BiomeInfo {
+            primary: Biome::try_from(primary as u32)?,
+            secondary: if secondary != 0 {
+                Some(Biome::try_from(secondary as u32)?)
+            } else {
+                None
+            },
+            blend,
+            flags,
+        })
+    }
+}
+
+/// A cell's packed biome density, laid out like its packed biome value
+///
+/// The low byte is the density of the cell's own biome and the second byte
+/// the density of the biome it borders. The upper bytes are unused.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Default)]
+pub struct BiomeDensity {
+    pub primary: u8,
+    pub secondary: u8,
+}
+
+impl BiomeDensity {
+    /// Share of the bordering biome in the cell, 0 when no density is set
+    pub fn secondary_weight(&self) -> f32 {
+        let total = self.primary as f32 + self.secondary as f32;
+        if total == 0.0 {
+            return 0.0;
+        }
+        self.secondary as f32 / total
+    }
+}
+
+impl From<u32> for BiomeDensity {
+    fn from(packed: u32) -> Self {
+        let [primary, secondary, ..] = packed.to_le_bytes();
+        BiomeDensity { primary, secondary }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_plain_biome() {
+        let info = BiomeInfo::try_from(0x0000_0003).unwrap();
+        assert_eq!(info.primary, Biome::SnowyPeaks);
+        assert_eq!(info.secondary, None);
+        assert_eq!((info.blend, info.flags), (0, 0));
+        assert!(!info.is_border() && !info.is_blended());
+    }
+
+    #[test]
+    fn decodes_border_and_blend() {
+        // Swamp bordering Ocean, blended at 0x40
+        let info = BiomeInfo::try_from(0x0040_0A08).unwrap();
+        assert_eq!(info.primary, Biome::Swamp);
+        assert_eq!(info.secondary, Some(Biome::Ocean));
+        assert_eq!((info.blend, info.flags), (0x40, 0));
+        assert!(info.is_border() && info.is_blended());
+    }
+
+    #[test]
+    fn flags_are_kept_raw() {
+        let info = BiomeInfo::try_from(0x8120_0001).unwrap();
+        assert_eq!(info.primary, Biome::CalmForest);
+        assert_eq!(info.secondary, None);
+        assert_eq!((info.blend, info.flags), (0x20, 0x81));
+        assert!(!info.is_border() && info.is_blended());
+    }
+
+    #[test]
+    fn rejects_unknown_ids() {
+        assert_eq!(BiomeInfo::try_from(0x0000_0020), Err(UnknownBiome(0x20)));
+        assert_eq!(BiomeInfo::try_from(0x0000_3001), Err(UnknownBiome(0x30)));
+    }
+
+    #[test]
+    fn decodes_density() {
+        let density = BiomeDensity::from(0xFFFF_3010);
+        assert_eq!((density.primary, density.secondary), (0x10, 0x30));
+        assert_eq!(density.secondary_weight(), 0.75);
+        assert_eq!(BiomeDensity::from(0).secondary_weight(), 0.0);
+    }
+}
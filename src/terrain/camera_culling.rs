@@ -1,6 +1,8 @@
 use bevy::math::Rect;
 use bevy::prelude::*;
 
+use crate::{log_targets::RENDERING, terrain::world_config::WorldConfig};
+
 /// Component to track chunks that should be rendered based on camera view
 #[derive(Component)]
 pub struct VisibleChunk;
@@ -12,33 +14,24 @@ pub struct ChunkBounds {
     pub world_bounds: Rect,
 }
 
-/// Calculate which chunks are visible from the camera
+/// World rectangle seen by the camera
+///
+/// The projection area already accounts for the window size and the zoom,
+/// Bevy updates it whenever the window is resized.
 pub fn calculate_visible_chunks(
     camera_transform: &Transform,
     camera_projection: &OrthographicProjection,
-    _chunk_size: f32, // World size of a chunk
 ) -> Rect {
-    // Calculate camera view bounds in world coordinates
     let camera_pos = camera_transform.translation.truncate();
-    let scale = camera_projection.scale;
-
-    // Assume window size for orthographic projection bounds
-    // In a real implementation, you'd get this from the window
-    let half_width = 500.0 * scale; // Half window width scaled
-    let half_height = 500.0 * scale; // Half window height scaled
+    let area = camera_projection.area;
 
-    Rect::new(
-        camera_pos.x - half_width,
-        camera_pos.y - half_height,
-        half_width * 2.0,
-        half_height * 2.0,
-    )
+    Rect::from_corners(camera_pos + area.min, camera_pos + area.max)
 }
 
-/// Check if a chunk intersects with the camera view
-pub fn chunk_in_view(chunk_bounds: &Rect, view_bounds: &Rect) -> bool {
-    // Add some padding to avoid pop-in at edges
-    let padding = 50.0;
+/// Check if a chunk intersects with the camera view grown by `padding`
+///
+/// The padding avoids pop-in at the edges.
+pub fn chunk_in_view(chunk_bounds: &Rect, view_bounds: &Rect, padding: f32) -> bool {
     let padded_view = Rect::new(
         view_bounds.min.x - padding,
         view_bounds.min.y - padding,
@@ -55,7 +48,8 @@ pub fn chunk_in_view(chunk_bounds: &Rect, view_bounds: &Rect) -> bool {
 
 /// System that updates chunk visibility based on camera position
 pub fn update_chunk_visibility(
-    camera_query: Query<(&Transform, &Projection), With<Camera>>,
+    camera_query: Query<(&Transform, &Projection), With<Camera2d>>,
+    world_config: Res<WorldConfig>,
     mut chunk_query: Query<(Entity, &ChunkBounds, Option<&VisibleChunk>)>,
     mut commands: Commands,
 ) {
@@ -69,14 +63,18 @@ pub fn update_chunk_visibility(
     };
 
     // Calculate current view bounds
-    let view_bounds = calculate_visible_chunks(camera_transform, camera_projection, 32.0 * 13.0);
+    let view_bounds = calculate_visible_chunks(camera_transform, camera_projection);
 
     let mut visible_count = 0;
     let mut hidden_count = 0;
 
     // Update visibility for each chunk
     for (entity, chunk_bounds, currently_visible) in chunk_query.iter_mut() {
-        let should_be_visible = chunk_in_view(&chunk_bounds.world_bounds, &view_bounds);
+        let should_be_visible = chunk_in_view(
+            &chunk_bounds.world_bounds,
+            &view_bounds,
+            world_config.cull_padding,
+        );
 
         match (currently_visible.is_some(), should_be_visible) {
             (false, true) => {
@@ -96,6 +94,11 @@ pub fn update_chunk_visibility(
 
     // Only log when visibility changes significantly
     if visible_count + hidden_count > 0 && (visible_count < 1000) {
-        log::debug!("Chunks: {} visible, {} hidden", visible_count, hidden_count);
+        log::debug!(
+            target: RENDERING,
+            "Chunks: {} visible, {} hidden",
+            visible_count,
+            hidden_count
+        );
     }
 }
@@ -1,7 +1,45 @@
+use hexx::Hex;
+
+use crate::terrain::{
+    biome::{BiomeDensity, BiomeInfo},
+    coords::CellCoord,
+    water::Water,
+};
+
 #[derive(Clone, PartialEq, Debug, Copy, Eq, Hash)]
 pub struct Cell {
     pub cell_x: i32,
     pub cell_z: i32,
     pub biome: u32,
+    pub biome_density: u32,
     pub elevation: i16,
+    pub water_level: i16,
+    pub water_body_type: u8,
+}
+
+impl Cell {
+    /// Position of the cell in its dimension's cell grid
+    pub fn coord(&self) -> CellCoord {
+        CellCoord::new(self.cell_x, self.cell_z)
+    }
+
+    /// Hex the cell is drawn at
+    pub fn hex(&self) -> Hex {
+        self.coord().hex()
+    }
+
+    /// Decoded biome of the cell, `None` for ids outside the known biomes
+    pub fn biome_info(&self) -> Option<BiomeInfo> {
+        BiomeInfo::try_from(self.biome).ok()
+    }
+
+    /// Decoded biome density of the cell
+    pub fn density(&self) -> BiomeDensity {
+        BiomeDensity::from(self.biome_density)
+    }
+
+    /// Water covering the cell, `None` when it is dry
+    pub fn water(&self) -> Option<Water> {
+        Water::at(self.elevation, self.water_level, self.water_body_type)
+    }
 }
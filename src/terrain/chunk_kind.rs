@@ -0,0 +1,267 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+};
+
+use crate::terrain::{chunk::TerrainChunkState, region_file::ChunkEntry};
+
+/// Whether a chunk belongs to the surface map or to an instanced space
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum ChunkKind {
+    /// Part of the contiguous surface map
+    Overworld,
+    /// Interior, cave or other instanced space, in its own dimension or
+    /// stored off the grid of a shared one
+    Instanced,
+}
+
+/// Identity and position of a chunk, everything the classifier looks at
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct ChunkKey {
+    pub chunk_index: u64,
+    pub dimension: u32,
+    pub chunk_x: i32,
+    pub chunk_z: i32,
+}
+
+impl ChunkKey {
+    /// Coordinates of the chunk in its dimension's chunk grid
+    pub fn coords(&self) -> (i32, i32) {
+        (self.chunk_x, self.chunk_z)
+    }
+}
+
+impl TerrainChunkState {
+    pub fn key(&self) -> ChunkKey {
+        ChunkKey {
+            chunk_index: self.chunk_index,
+            dimension: self.dimension,
+            chunk_x: self.chunk_x,
+            chunk_z: self.chunk_z,
+        }
+    }
+}
+
+impl ChunkEntry {
+    pub fn key(&self) -> ChunkKey {
+        ChunkKey {
+            chunk_index: self.chunk_index,
+            dimension: self.dimension,
+            chunk_x: self.chunk_x,
+            chunk_z: self.chunk_z,
+        }
+    }
+}
+
+/// How the chunk indices of a dimension follow from chunk coordinates,
+/// `chunk_index = base + chunk_z * stride + chunk_x`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct IndexLayout {
+    pub base: i64,
+    pub stride: i64,
+}
+
+impl IndexLayout {
+    /// Whether a chunk is numbered like the rest of its grid
+    pub fn fits(&self, key: &ChunkKey) -> bool {
+        key.chunk_index as i64 == self.base + key.chunk_z as i64 * self.stride + key.chunk_x as i64
+    }
+
+    /// Find the layout most chunks of one dimension agree on
+    ///
+    /// Neighbouring chunks of each column vote for the stride, then every chunk
+    /// votes for the base. The layout is only accepted when it explains more
+    /// than half of the chunks, a dimension numbered any other way gets none.
+    pub fn detect(keys: &[ChunkKey]) -> Option<Self> {
+        let mut columns: HashMap<i32, Vec<(i32, i64)>> = HashMap::new();
+        for key in keys {
+            columns
+                .entry(key.chunk_x)
+                .or_default()
+                .push((key.chunk_z, key.chunk_index as i64));
+        }
+
+        let mut stride_votes: HashMap<i64, usize> = HashMap::new();
+        for column in columns.values_mut() {
+            column.sort_unstable();
+            for pair in column.windows(2) {
+                let ((z0, index0), (z1, index1)) = (pair[0], pair[1]);
+                let dz = (z1 - z0) as i64;
+                // Chunks sharing coordinates say nothing about the stride
+                if dz != 0 && (index1 - index0) % dz == 0 {
+                    *stride_votes.entry((index1 - index0) / dz).or_default() += 1;
+                }
+            }
+        }
+        let stride = most_voted(stride_votes)?.0;
+
+        let mut base_votes: HashMap<i64, usize> = HashMap::new();
+        for key in keys {
+            let base = key.chunk_index as i64 - key.chunk_z as i64 * stride - key.chunk_x as i64;
+            *base_votes.entry(base).or_default() += 1;
+        }
+        let (base, count) = most_voted(base_votes)?;
+
+        (count * 2 > keys.len()).then_some(Self { base, stride })
+    }
+}
+
+/// The value with the most votes, ties going to the smallest value
+fn most_voted(votes: HashMap<i64, usize>) -> Option<(i64, usize)> {
+    votes
+        .into_iter()
+        .max_by_key(|&(value, count)| (count, Reverse(value)))
+}
+
+/// Separates the surface map from instanced spaces
+///
+/// Instanced spaces either live in a dimension of their own or share one
+/// while reusing small chunk coordinates, usually starting at the origin.
+/// Chunks of the second kind are recognised by a `chunk_index` that does not
+/// follow the numbering of their dimension's grid. The overworld is the
+/// dimension with the most chunks on its grid, since the surface map dwarfs
+/// every instanced space in any dump holding a meaningful part of the world.
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct ChunkClassifier {
+    /// Dimension of the surface map, `None` until chunks have been classified
+    pub overworld_dimension: Option<u32>,
+    /// Index layout of every dimension that follows one
+    pub layouts: HashMap<u32, IndexLayout>,
+}
+
+impl ChunkClassifier {
+    /// Learn the index layouts and the overworld from every loaded chunk
+    pub fn detect(keys: &[ChunkKey]) -> Self {
+        let mut by_dimension: BTreeMap<u32, Vec<ChunkKey>> = BTreeMap::new();
+        for key in keys {
+            by_dimension.entry(key.dimension).or_default().push(*key);
+        }
+
+        let mut classifier = Self::default();
+        for (&dimension, keys) in &by_dimension {
+            if let Some(layout) = IndexLayout::detect(keys) {
+                classifier.layouts.insert(dimension, layout);
+            }
+        }
+
+        classifier.overworld_dimension = by_dimension
+            .iter()
+            .map(|(&dimension, keys)| {
+                let on_grid = keys.iter().filter(|key| classifier.on_grid(key)).count();
+                (dimension, on_grid)
+            })
+            .max_by_key(|&(dimension, on_grid)| (on_grid, Reverse(dimension)))
+            .map(|(dimension, _)| dimension);
+
+        classifier
+    }
+
+    /// Whether a chunk sits on its dimension's grid rather than being an
+    /// instanced space stored at reused coordinates
+    ///
+    /// Without a detected layout every chunk counts as on the grid, and only
+    /// coordinate collisions tell interiors apart.
+    pub fn on_grid(&self, key: &ChunkKey) -> bool {
+        self.layouts
+            .get(&key.dimension)
+            .is_none_or(|layout| layout.fits(key))
+    }
+
+    pub fn classify(&self, key: &ChunkKey) -> ChunkKind {
+        if self.classify_dimension(key.dimension) == ChunkKind::Overworld && self.on_grid(key) {
+            ChunkKind::Overworld
+        } else {
+            ChunkKind::Instanced
+        }
+    }
+
+    /// Kind of the chunks on a dimension's grid
+    pub fn classify_dimension(&self, dimension: u32) -> ChunkKind {
+        if self.overworld_dimension == Some(dimension) {
+            ChunkKind::Overworld
+        } else {
+            ChunkKind::Instanced
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(chunk_index: u64, dimension: u32, chunk_x: i32, chunk_z: i32) -> ChunkKey {
+        ChunkKey {
+            chunk_index,
+            dimension,
+            chunk_x,
+            chunk_z,
+        }
+    }
+
+    /// A 4x3 grid numbered row by row from 100, plus two interiors at the origin
+    fn surface_with_interiors() -> Vec<ChunkKey> {
+        let mut keys: Vec<ChunkKey> = (0..3)
+            .flat_map(|z| (0..4).map(move |x| key(100 + z as u64 * 4 + x as u64, 2, x, z)))
+            .collect();
+        keys.push(key(9_000, 2, 0, 0));
+        keys.push(key(9_001, 2, 0, 0));
+        keys
+    }
+
+    #[test]
+    fn detects_row_major_layout() {
+        let keys = surface_with_interiors();
+        assert_eq!(
+            IndexLayout::detect(&keys),
+            Some(IndexLayout {
+                base: 100,
+                stride: 4,
+            })
+        );
+    }
+
+    #[test]
+    fn interiors_at_origin_are_off_grid() {
+        let classifier = ChunkClassifier::detect(&surface_with_interiors());
+        assert_eq!(
+            classifier.classify(&key(100, 2, 0, 0)),
+            ChunkKind::Overworld
+        );
+        assert_eq!(
+            classifier.classify(&key(9_000, 2, 0, 0)),
+            ChunkKind::Instanced
+        );
+        assert_eq!(
+            classifier.classify(&key(111, 2, 3, 2)),
+            ChunkKind::Overworld
+        );
+    }
+
+    #[test]
+    fn overworld_is_largest_grid() {
+        let mut keys = surface_with_interiors();
+        // A cave dimension with more chunks than interiors but fewer than the surface
+        keys.extend((0..5).map(|x| key(500 + x as u64, 7, x, 0)));
+
+        let classifier = ChunkClassifier::detect(&keys);
+        assert_eq!(classifier.overworld_dimension, Some(2));
+        assert_eq!(classifier.classify_dimension(7), ChunkKind::Instanced);
+    }
+
+    #[test]
+    fn no_layout_without_majority() {
+        let keys = [
+            key(5, 1, 0, 0),
+            key(17, 1, 0, 1),
+            key(3, 1, 1, 2),
+            key(40, 1, 2, 0),
+        ];
+        assert_eq!(IndexLayout::detect(&keys), None);
+        assert!(ChunkClassifier::detect(&keys).on_grid(&keys[2]));
+    }
+
+    #[test]
+    fn empty_dump_has_no_overworld() {
+        assert_eq!(ChunkClassifier::detect(&[]).overworld_dimension, None);
+    }
+}
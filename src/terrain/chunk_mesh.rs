@@ -5,57 +5,136 @@ use bevy::{
 };
 use hexx::*;
 
-use crate::terrain::{chunk::TerrainChunkState, color_utils::calculate_hex_color};
+use crate::terrain::{
+    cell::Cell,
+    color_utils::{calculate_hex_color, land_color, water_color},
+    hillshade::{Heightfield, Hillshade},
+    palette::BiomePalette,
+};
+
+/// World units per elevation unit in the 3D view
+pub const COLUMN_HEIGHT_SCALE: f32 = 4.0;
+
+/// Elevation hex columns start from in the 3D view
+pub const COLUMN_FLOOR: f32 = -10.0;
 
 /// Component for chunk-level mesh entities
-#[derive(Component)]
+#[derive(Component, Clone, Debug)]
 pub struct ChunkMesh {
     pub chunk_coords: (i32, i32),
     pub hex_count: usize,
     pub bounds: Rect,
 }
 
+/// Flat hex geometry of a layout, shared by every hex of the 2D map
+///
+/// Built once per layout and copied into chunk meshes at each hex's
+/// position, instead of generating the same hex for every cell.
+#[derive(Clone, Debug)]
+pub struct HexTemplate {
+    layout: HexLayout,
+    vertices: Vec<[f32; 3]>,
+    indices: Vec<u32>,
+}
+
+impl HexTemplate {
+    pub fn new(layout: &HexLayout) -> Self {
+        let mesh_info = PlaneMeshBuilder::new(layout)
+            .facing(Vec3::Z)
+            .with_scale(Vec3::splat(0.98))
+            .center_aligned()
+            .build();
+
+        Self {
+            layout: layout.clone(),
+            vertices: mesh_info.vertices.iter().map(|v| v.to_array()).collect(),
+            indices: mesh_info.indices.iter().map(|&i| i as u32).collect(),
+        }
+    }
+
+    /// Layout the template was built for
+    pub fn layout(&self) -> &HexLayout {
+        &self.layout
+    }
+
+    /// Vertex positions relative to the hex centre
+    pub fn vertices(&self) -> &[[f32; 3]] {
+        &self.vertices
+    }
+
+    /// Triangle indices into `vertices`
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+}
+
 /// Builder for combining multiple hex meshes into a single chunk mesh
+///
+/// Flat hexes only carry positions and colors, which is all the 2D material
+/// reads. Normals are only stored for columns, which the 3D view lights.
+#[derive(Default)]
 pub struct ChunkMeshBuilder {
-    vertices: Vec<Vec3>,
+    vertices: Vec<[f32; 3]>,
     colors: Vec<[f32; 4]>,
-    normals: Vec<Vec3>,
-    uvs: Vec<Vec2>,
+    normals: Vec<[f32; 3]>,
     indices: Vec<u32>,
-    current_vertex_offset: u32,
+    hex_count: usize,
 }
 
 impl ChunkMeshBuilder {
-    pub fn new() -> Self {
+    /// Builder with room for `hex_count` flat hexes of `template`
+    pub fn with_capacity(template: &HexTemplate, hex_count: usize) -> Self {
+        let vertex_count = template.vertices.len() * hex_count;
         Self {
-            vertices: Vec::new(),
-            colors: Vec::new(),
+            vertices: Vec::with_capacity(vertex_count),
+            colors: Vec::with_capacity(vertex_count),
             normals: Vec::new(),
-            uvs: Vec::new(),
-            indices: Vec::new(),
-            current_vertex_offset: 0,
+            indices: Vec::with_capacity(template.indices.len() * hex_count),
+            hex_count: 0,
         }
     }
 
-    /// Add a hex mesh to the combined mesh at the specified world position
-    pub fn add_hex(&mut self, layout: &HexLayout, world_pos: Vec2, color: Color) {
-        // Generate hex mesh data
-        let mesh_info = PlaneMeshBuilder::new(layout)
-            .facing(Vec3::Z)
-            .with_scale(Vec3::splat(0.98))
-            .center_aligned()
-            .build();
+    /// Add a flat hex to the combined mesh at the specified world position
+    ///
+    /// `shade` scales the color's brightness, 1 leaves it unchanged.
+    pub fn add_hex(&mut self, template: &HexTemplate, world_pos: Vec2, color: Color, shade: f32) {
+        let linear_color = color.to_linear();
+        let color_array = [
+            (linear_color.red * shade).min(1.0),
+            (linear_color.green * shade).min(1.0),
+            (linear_color.blue * shade).min(1.0),
+            linear_color.alpha,
+        ];
 
-        let vertex_count = mesh_info.vertices.len() as u32;
+        let first = self.vertices.len() as u32;
+        self.vertices.extend(
+            template
+                .vertices
+                .iter()
+                .map(|&[x, y, z]| [x + world_pos.x, y + world_pos.y, z]),
+        );
+        self.colors
+            .extend(std::iter::repeat_n(color_array, template.vertices.len()));
+        self.indices
+            .extend(template.indices.iter().map(|&index| index + first));
+        self.hex_count += 1;
+    }
 
-        // Transform vertices to world position and add to combined mesh
-        for vertex in mesh_info.vertices {
-            let world_vertex =
-                Vec3::new(vertex[0] + world_pos.x, vertex[1] + world_pos.y, vertex[2]);
-            self.vertices.push(world_vertex);
-        }
+    /// Add a hex column rising along Z from `base` to `top` at the specified
+    /// world position, for the 3D view
+    pub fn add_column(
+        &mut self,
+        layout: &HexLayout,
+        world_pos: Vec2,
+        base: f32,
+        top: f32,
+        color: Color,
+    ) {
+        let mesh_info = ColumnMeshBuilder::new(layout, (top - base).max(0.0))
+            .facing(Vec3::Z)
+            .without_bottom_face()
+            .build();
 
-        // Add vertex colors (same color for all vertices of this hex)
         let linear_color = color.to_linear();
         let color_array = [
             linear_color.red,
@@ -63,27 +142,18 @@ impl ChunkMeshBuilder {
             linear_color.blue,
             linear_color.alpha,
         ];
-        for _ in 0..vertex_count {
-            self.colors.push(color_array);
-        }
-
-        // Add normals
-        for normal in mesh_info.normals {
-            self.normals
-                .push(Vec3::new(normal[0], normal[1], normal[2]));
-        }
-
-        // Add UVs
-        for uv in mesh_info.uvs {
-            self.uvs.push(Vec2::new(uv[0], uv[1]));
-        }
-
-        // Add indices with vertex offset
-        for index in mesh_info.indices {
-            self.indices.push(index as u32 + self.current_vertex_offset);
-        }
 
-        self.current_vertex_offset += vertex_count;
+        let offset = world_pos.extend(base);
+        let first = self.vertices.len() as u32;
+        self.vertices
+            .extend(mesh_info.vertices.iter().map(|&v| (v + offset).to_array()));
+        self.colors
+            .extend(std::iter::repeat_n(color_array, mesh_info.vertices.len()));
+        self.normals
+            .extend(mesh_info.normals.iter().map(|n| n.to_array()));
+        self.indices
+            .extend(mesh_info.indices.iter().map(|&index| index as u32 + first));
+        self.hex_count += 1;
     }
 
     /// Build the final combined mesh
@@ -93,15 +163,11 @@ impl ChunkMeshBuilder {
             RenderAssetUsages::RENDER_WORLD,
         );
 
-        // Convert Vec3 to [f32; 3] for mesh attributes
-        let vertices: Vec<[f32; 3]> = self.vertices.iter().map(|v| v.to_array()).collect();
-        let normals: Vec<[f32; 3]> = self.normals.iter().map(|n| n.to_array()).collect();
-        let uvs: Vec<[f32; 2]> = self.uvs.iter().map(|uv| uv.to_array()).collect();
-
-        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vertices);
-        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
-        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.vertices);
         mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, self.colors);
+        if !self.normals.is_empty() {
+            mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals);
+        }
         mesh.insert_indices(Indices::U32(self.indices));
 
         mesh
@@ -109,18 +175,24 @@ impl ChunkMeshBuilder {
 
     /// Get the number of hexes added to this mesh
     pub fn hex_count(&self) -> usize {
-        (self.current_vertex_offset / 6) as usize // Assuming 6 vertices per hex
+        self.hex_count
     }
 }
 
 /// Create a batched mesh for an entire chunk
+///
+/// `cells` are usually a chunk's cells, or the down-sampled cells of a
+/// superchunk.
 pub fn create_chunk_mesh(
-    chunk: &TerrainChunkState,
-    layout: &HexLayout,
+    cells: &[Cell],
+    chunk_coords: (i32, i32),
+    template: &HexTemplate,
     center_offset: Vec2,
+    palette: &BiomePalette,
+    shading: Option<(&Hillshade, &Heightfield)>,
 ) -> (Mesh, ChunkMesh) {
-    let mut builder = ChunkMeshBuilder::new();
-    let cells = chunk.cells();
+    let layout = template.layout();
+    let mut builder = ChunkMeshBuilder::with_capacity(template, cells.len());
 
     let mut min_x = f32::MAX;
     let mut max_x = f32::MIN;
@@ -128,9 +200,8 @@ pub fn create_chunk_mesh(
     let mut max_y = f32::MIN;
 
     // Add each hex in the chunk to the combined mesh
-    for cell in &cells {
-        let hex = layout.world_pos_to_hex(Vec2::new(cell.cell_x as f32, cell.cell_z as f32));
-        let pos = layout.hex_to_world_pos(hex);
+    for cell in cells {
+        let pos = layout.hex_to_world_pos(cell.hex());
 
         // Apply center offset to center the map around (0,0)
         let world_pos = pos - center_offset;
@@ -142,9 +213,12 @@ pub fn create_chunk_mesh(
         max_y = max_y.max(world_pos.y);
 
         // Calculate biome-based color
-        let color = calculate_hex_color(cell.biome, cell.elevation);
+        let color = calculate_hex_color(palette, cell);
+        let shade = shading.map_or(1.0, |(hillshade, heights)| {
+            hillshade.shade(heights, layout, cell)
+        });
 
-        builder.add_hex(layout, world_pos, color);
+        builder.add_hex(template, world_pos, color, shade);
     }
 
     let chunk_bounds = Rect::new(min_x, min_y, max_x - min_x, max_y - min_y);
@@ -152,10 +226,56 @@ pub fn create_chunk_mesh(
 
     let mesh = builder.build();
     let chunk_component = ChunkMesh {
-        chunk_coords: (chunk.chunk_x, chunk.chunk_z),
+        chunk_coords,
         hex_count,
         bounds: chunk_bounds,
     };
 
     (mesh, chunk_component)
 }
+
+/// Create a batched mesh of hex columns for an entire chunk, for the 3D view
+///
+/// Each cell rises to its elevation, water fills submerged cells up to their
+/// water level.
+pub fn create_column_mesh(
+    cells: &[Cell],
+    chunk_coords: (i32, i32),
+    layout: &HexLayout,
+    center_offset: Vec2,
+    palette: &BiomePalette,
+) -> (Mesh, ChunkMesh) {
+    let mut builder = ChunkMeshBuilder::default();
+    let floor = COLUMN_FLOOR * COLUMN_HEIGHT_SCALE;
+
+    let mut min = Vec2::MAX;
+    let mut max = Vec2::MIN;
+
+    for cell in cells {
+        let world_pos = layout.hex_to_world_pos(cell.hex()) - center_offset;
+        min = min.min(world_pos);
+        max = max.max(world_pos);
+
+        let ground = (cell.elevation as f32 * COLUMN_HEIGHT_SCALE).max(floor);
+        builder.add_column(layout, world_pos, floor, ground, land_color(palette, cell));
+
+        if let Some(water) = cell.water() {
+            let surface = cell.water_level as f32 * COLUMN_HEIGHT_SCALE;
+            builder.add_column(
+                layout,
+                world_pos,
+                ground,
+                surface,
+                water_color(palette, &water),
+            );
+        }
+    }
+
+    let chunk_component = ChunkMesh {
+        chunk_coords,
+        hex_count: cells.len(),
+        bounds: Rect::from_corners(min, max),
+    };
+
+    (builder.build(), chunk_component)
+}
@@ -1,40 +1,60 @@
-use crate::terrain::biome_colors_range::{BIOME_COLORS, ColorRange};
-use bevy::prelude::Color;
+use crate::terrain::{
+    biome_colors_range::ColorRange, cell::Cell, palette::BiomePalette, water::Water,
+};
+use bevy::{color::Mix, prelude::Color};
 
-/// Water color for hexes below elevation 0
+/// Water color for palettes that do not set their own
 pub const WATER_COLOR: Color = Color::srgb_u8(0, 105, 148);
 
 /// Calculates the color for a hex based on biome and elevation
 ///
+/// Border cells mix in the color of the biome they border, weighted by the
+/// cell's biome density, so biomes fade into each other instead of changing
+/// color at a hex edge. Cells below their water level take the color of their
+/// water body, darker the deeper the water.
+///
 /// # Arguments
-/// * `biome` - The biome type as u32
-/// * `elevation` - The elevation value as i16
+/// * `palette` - The palette to take the biome's color range from
+/// * `cell` - The cell with its packed biome, density, elevation and water
 ///
 /// # Returns
 /// The calculated Color based on biome and elevation
-pub fn calculate_hex_color(biome: u32, elevation: i16) -> Color {
-    // Handle water (below elevation 0)
-    if elevation < 0 {
-        return WATER_COLOR;
+pub fn calculate_hex_color(palette: &BiomePalette, cell: &Cell) -> Color {
+    match cell.water() {
+        Some(water) => water_color(palette, &water),
+        None => land_color(palette, cell),
     }
+}
 
-    // Get the color range for this biome
-    let color_range = match BIOME_COLORS.get(&biome) {
-        Some(range) => *range,
-        None => {
-            // Fallback to a default color if biome not found
-            ColorRange {
-                start: Color::srgb_u8(128, 128, 128),
-                end: Color::srgb_u8(200, 200, 200),
-            }
-        }
-    };
+/// Color of the water covering a cell, darker the deeper it is
+pub fn water_color(palette: &BiomePalette, water: &Water) -> Color {
+    range_color(palette.water_range(water.body), water.normalized_depth())
+}
 
+/// Color of a cell's ground, ignoring any water above it
+pub fn land_color(palette: &BiomePalette, cell: &Cell) -> Color {
     // Normalize elevation to a 0-1 range for interpolation
     // Using actual elevation range (0 to 50) based on game data
-    let normalized_elevation = (elevation as f32 / 50.0).clamp(0.0, 1.0);
+    let normalized_elevation = (cell.elevation as f32 / 50.0).clamp(0.0, 1.0);
+
+    // Color of the cell's own biome, or the palette's entry for its border
+    let color = range_color(palette.color_range(cell.biome), normalized_elevation);
+
+    let Some(secondary) = cell.biome_info().and_then(|info| info.secondary) else {
+        return color;
+    };
+    let weight = cell.density().secondary_weight();
+    if weight == 0.0 {
+        return color;
+    }
+
+    let secondary_color = range_color(palette.biome_range(secondary), normalized_elevation);
+    Color::from(color.to_linear().mix(&secondary_color.to_linear(), weight))
+}
 
-    // Interpolate between start and end colors based on elevation
+/// Color at `t` between the start (0) and end (1) of a color range
+fn range_color(color_range: ColorRange, t: f32) -> Color {
+    // Interpolate between start and end colors
     let start = color_range.start;
     let end = color_range.end;
 
@@ -43,9 +63,9 @@ pub fn calculate_hex_color(biome: u32, elevation: i16) -> Color {
     let end_linear = end.to_linear();
 
     Color::srgb(
-        start_linear.red + (end_linear.red - start_linear.red) * normalized_elevation,
-        start_linear.green + (end_linear.green - start_linear.green) * normalized_elevation,
-        start_linear.blue + (end_linear.blue - start_linear.blue) * normalized_elevation,
+        start_linear.red + (end_linear.red - start_linear.red) * t,
+        start_linear.green + (end_linear.green - start_linear.green) * t,
+        start_linear.blue + (end_linear.blue - start_linear.blue) * t,
     )
 }
 
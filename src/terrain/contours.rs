@@ -0,0 +1,355 @@
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_asset::RenderAssetUsages, render_resource::PrimitiveTopology},
+};
+use hexx::{Hex, HexLayout};
+use std::collections::HashMap;
+
+use crate::{
+    cli::ViewerOptions,
+    log_targets::RENDERING,
+    terrain::{
+        chunk::TerrainChunkState,
+        dynamic_chunks::{DynamicChunk, SpawnedChunks, despawn_all_chunks},
+        hillshade::Heightfield,
+        instanced::Renderer,
+        view_mode::ViewMode,
+    },
+};
+
+/// Height of the contour layer above the chunk meshes
+pub const CONTOUR_LAYER_Z: f32 = 1.0;
+
+/// Marks the contour line child of a chunk entity
+#[derive(Component)]
+pub struct ContourLayer;
+
+/// Contour line settings, toggled with `C`
+#[derive(Resource, Clone, Copy, PartialEq, Debug)]
+pub struct Contours {
+    pub enabled: bool,
+    /// Elevation difference between neighbouring contour lines
+    pub interval: i16,
+    /// Every this many contour lines is drawn as a major line
+    pub major_every: i16,
+    pub minor_width: f32,
+    pub major_width: f32,
+    pub color: Color,
+}
+
+impl Default for Contours {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: 5,
+            major_every: 5,
+            minor_width: 1.5,
+            major_width: 3.5,
+            color: Color::srgba(0.15, 0.1, 0.05, 0.7),
+        }
+    }
+}
+
+impl Contours {
+    /// Settings requested on the command line
+    pub fn from_options(options: &ViewerOptions) -> Self {
+        match options.contour_interval {
+            Some(interval) => Self {
+                enabled: true,
+                interval,
+                ..default()
+            },
+            None => Self::default(),
+        }
+    }
+
+    /// Band index of the contour drawn on the edge between two elevations and
+    /// its line width, `None` when no contour runs between them
+    ///
+    /// Where several contours cross one edge, as on cliffs, a major one is
+    /// drawn in preference to the lowest minor one.
+    pub fn crossing(&self, a: i16, b: i16) -> Option<(i32, f32)> {
+        let interval = self.interval.max(1) as i32;
+        let low_band = (a.min(b) as i32).div_euclid(interval);
+        let high_band = (a.max(b) as i32).div_euclid(interval);
+        if low_band == high_band {
+            return None;
+        }
+
+        let major_every = self.major_every.max(1) as i32;
+        match (low_band + 1..=high_band).find(|band| band % major_every == 0) {
+            Some(band) => Some((band, self.major_width)),
+            None => Some((low_band + 1, self.minor_width)),
+        }
+    }
+}
+
+/// Mesh of the contour lines along a chunk's hex edges, `None` without lines
+///
+/// Each cell contributes its edges towards three of its neighbours, the other
+/// three belong to the neighbouring cells, so lines across chunk borders are
+/// only drawn once. Edges of the same contour are joined into polylines so
+/// lines bend at hex corners without gaps or overlaps.
+pub fn create_contour_mesh(
+    chunk: &TerrainChunkState,
+    heights: &Heightfield,
+    layout: &HexLayout,
+    center_offset: Vec2,
+    contours: &Contours,
+) -> Option<Mesh> {
+    // Corner pairs facing each neighbour, the same for every hex
+    let origin = layout.hex_to_world_pos(Hex::ZERO);
+    let corners = layout.hex_corners(Hex::ZERO);
+    let edges: Vec<_> = Hex::NEIGHBORS_COORDS[..3]
+        .iter()
+        .map(|&offset| {
+            let direction = layout.hex_to_world_pos(offset) - origin;
+            let facing =
+                |i: usize| (corners[i] + corners[(i + 1) % 6] - 2.0 * origin).dot(direction);
+            let corner = (0..6)
+                .max_by(|&a, &b| facing(a).total_cmp(&facing(b)))
+                .unwrap_or_default();
+            (offset, corner)
+        })
+        .collect();
+
+    // Edge segments grouped by contour band, each band with its line width
+    let mut bands: HashMap<i32, (f32, Vec<(Vec2, Vec2)>)> = HashMap::new();
+    for cell in chunk.cells() {
+        let cell_coords = cell.hex();
+        let drawn_corners = layout.hex_corners(cell_coords);
+
+        for &(offset, corner) in &edges {
+            let Some(neighbor_elevation) = heights.elevation(cell_coords + offset) else {
+                continue;
+            };
+            let Some((band, width)) = contours.crossing(cell.elevation, neighbor_elevation) else {
+                continue;
+            };
+
+            let start = drawn_corners[corner] - center_offset;
+            let end = drawn_corners[(corner + 1) % 6] - center_offset;
+            bands
+                .entry(band)
+                .or_insert((width, Vec::new()))
+                .1
+                .push((start, end));
+        }
+    }
+
+    let linear = contours.color.to_linear();
+    let color = [linear.red, linear.green, linear.blue, linear.alpha];
+    let mut vertices: Vec<[f32; 3]> = Vec::new();
+    let mut indices = Vec::new();
+
+    for (width, segments) in bands.into_values() {
+        for polyline in join_segments(&segments) {
+            push_polyline(&polyline, width, &mut vertices, &mut indices);
+        }
+    }
+
+    if vertices.is_empty() {
+        return None;
+    }
+
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, vec![color; vertices.len()]);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vertices);
+    mesh.insert_indices(Indices::U32(indices));
+
+    Some(mesh)
+}
+
+/// A chain of connected points, closed polylines end where they start
+#[derive(Clone, PartialEq, Debug)]
+pub struct Polyline {
+    pub points: Vec<Vec2>,
+    pub closed: bool,
+}
+
+/// Corner position rounded so corners shared by neighbouring hexes compare equal
+fn point_key(point: Vec2) -> (i64, i64) {
+    (
+        (point.x * 64.0).round() as i64,
+        (point.y * 64.0).round() as i64,
+    )
+}
+
+/// Join segments sharing end points into polylines
+///
+/// Where more than two segments meet, one polyline continues through the
+/// point and the others start a new one there.
+pub fn join_segments(segments: &[(Vec2, Vec2)]) -> Vec<Polyline> {
+    let mut at_point: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    for (index, &(start, end)) in segments.iter().enumerate() {
+        at_point.entry(point_key(start)).or_default().push(index);
+        at_point.entry(point_key(end)).or_default().push(index);
+    }
+
+    let mut used = vec![false; segments.len()];
+    // Next unused segment at a point, with the end point it leads to
+    let next_from = |point: Vec2, used: &mut [bool]| {
+        let candidates = at_point.get(&point_key(point))?;
+        let &index = candidates.iter().find(|&&index| !used[index])?;
+        used[index] = true;
+        let (start, end) = segments[index];
+        Some(if point_key(start) == point_key(point) {
+            end
+        } else {
+            start
+        })
+    };
+
+    let mut polylines = Vec::new();
+    for (index, &(start, end)) in segments.iter().enumerate() {
+        if used[index] {
+            continue;
+        }
+        used[index] = true;
+
+        let mut forward = vec![start, end];
+        while let Some(point) = next_from(*forward.last().unwrap(), &mut used) {
+            forward.push(point);
+        }
+        let mut backward = Vec::new();
+        let mut head = start;
+        while let Some(point) = next_from(head, &mut used) {
+            backward.push(point);
+            head = point;
+        }
+
+        backward.reverse();
+        backward.extend(forward);
+        let mut points = backward;
+
+        let closed =
+            points.len() > 3 && point_key(points[0]) == point_key(points[points.len() - 1]);
+        if closed {
+            points.pop();
+        }
+        polylines.push(Polyline { points, closed });
+    }
+
+    polylines
+}
+
+/// Triangles of a polyline of the given width, mitred at every bend
+fn push_polyline(
+    polyline: &Polyline,
+    width: f32,
+    vertices: &mut Vec<[f32; 3]>,
+    indices: &mut Vec<u32>,
+) {
+    let points = &polyline.points;
+    let count = points.len();
+    if count < 2 {
+        return;
+    }
+
+    let normal = |from: Vec2, to: Vec2| (to - from).perp().normalize_or_zero();
+    let first = vertices.len() as u32;
+    for (i, &point) in points.iter().enumerate() {
+        let previous = match i {
+            0 if polyline.closed => Some(points[count - 1]),
+            0 => None,
+            _ => Some(points[i - 1]),
+        };
+        let next = if i + 1 < count {
+            Some(points[i + 1])
+        } else if polyline.closed {
+            Some(points[0])
+        } else {
+            None
+        };
+
+        let side = match (previous, next) {
+            (Some(previous), Some(next)) => {
+                let (before, after) = (normal(previous, point), normal(point, next));
+                let miter = (before + after).normalize_or(before);
+                // Keep the line width on both segments, capped for sharp bends
+                miter * (width * 0.5 / miter.dot(before).max(0.5))
+            }
+            (None, Some(next)) => normal(point, next) * width * 0.5,
+            (Some(previous), None) => normal(previous, point) * width * 0.5,
+            (None, None) => Vec2::ZERO,
+        };
+        vertices.push([point.x - side.x, point.y - side.y, 0.0]);
+        vertices.push([point.x + side.x, point.y + side.y, 0.0]);
+    }
+
+    let segment_count = if polyline.closed { count } else { count - 1 };
+    for i in 0..segment_count as u32 {
+        let a = first + 2 * i;
+        let b = first + 2 * ((i + 1) % count as u32);
+        indices.extend([a, a + 1, b + 1, a, b + 1, b]);
+    }
+}
+
+/// System toggling the contour overlay with `C`
+pub fn toggle_contours(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut contours: ResMut<Contours>,
+    renderer: Res<Renderer>,
+    view_mode: Res<ViewMode>,
+    mut spawned_chunks: ResMut<SpawnedChunks>,
+    chunk_query: Query<Entity, With<DynamicChunk>>,
+) {
+    if !keyboard.just_pressed(KeyCode::KeyC) {
+        return;
+    }
+
+    contours.enabled = !contours.enabled;
+    if !renderer.recolors_on_gpu(*view_mode) {
+        despawn_all_chunks(&mut commands, &mut spawned_chunks, &chunk_query);
+    }
+
+    log::info!(
+        target: RENDERING,
+        "Contours {} every {} elevation",
+        if contours.enabled { "on" } else { "off" },
+        contours.interval
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn major_contour_wins_on_cliffs() {
+        let contours = Contours::default();
+        assert_eq!(contours.crossing(3, 4), None);
+        assert_eq!(contours.crossing(4, 6), Some((1, contours.minor_width)));
+        assert_eq!(contours.crossing(31, 4), Some((5, contours.major_width)));
+    }
+
+    #[test]
+    fn joins_segments_in_any_direction() {
+        let (a, b, c, d) = (Vec2::ZERO, Vec2::X, Vec2::new(1.0, 1.0), Vec2::Y * 3.0);
+        let polylines = join_segments(&[(b, c), (b, a), (d, c)]);
+        assert_eq!(polylines.len(), 1);
+        let points = &polylines[0].points;
+        assert!(points == &[a, b, c, d] || points == &[d, c, b, a]);
+        assert!(!polylines[0].closed);
+    }
+
+    #[test]
+    fn joins_closed_rings() {
+        let ring = [Vec2::ZERO, Vec2::X, Vec2::new(1.0, 1.0), Vec2::Y];
+        let segments: Vec<_> = (0..4).map(|i| (ring[i], ring[(i + 1) % 4])).collect();
+        let polylines = join_segments(&segments);
+        assert_eq!(polylines.len(), 1);
+        assert!(polylines[0].closed);
+        assert_eq!(polylines[0].points.len(), 4);
+    }
+
+    #[test]
+    fn separate_lines_stay_apart() {
+        let segments = [(Vec2::ZERO, Vec2::X), (Vec2::Y * 5.0, Vec2::new(1.0, 5.0))];
+        assert_eq!(join_segments(&segments).len(), 2);
+    }
+}
@@ -0,0 +1,239 @@
+use bevy::prelude::*;
+use hexx::{Hex, HexLayout};
+
+/// Cells along each side of a chunk
+pub const CHUNK_SIDE: i32 = 32;
+
+/// Hex size in world units, from the centre to a corner
+pub const HEX_SIZE: f32 = 13.0;
+
+/// Position of a chunk in its dimension's chunk grid
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub struct ChunkCoord {
+    pub x: i32,
+    pub z: i32,
+}
+
+impl ChunkCoord {
+    pub const fn new(x: i32, z: i32) -> Self {
+        Self { x, z }
+    }
+
+    /// Cell at `index` of the chunk's per-cell fields
+    ///
+    /// Fields are stored one x column after another, so
+    /// `index = local_x * side + local_z`.
+    pub fn cell(self, side: i32, index: usize) -> CellCoord {
+        let index = index as i32;
+        CellCoord::new(self.x * side + index / side, self.z * side + index % side)
+    }
+}
+
+impl From<(i32, i32)> for ChunkCoord {
+    fn from((x, z): (i32, i32)) -> Self {
+        Self::new(x, z)
+    }
+}
+
+impl From<ChunkCoord> for (i32, i32) {
+    fn from(chunk: ChunkCoord) -> Self {
+        (chunk.x, chunk.z)
+    }
+}
+
+/// Position of a cell in its dimension's cell grid
+///
+/// Cells are laid out in offset coordinates: rows of hexes along x, one row
+/// per z, with every odd row shifted half a hex towards +x.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub struct CellCoord {
+    pub x: i32,
+    pub z: i32,
+}
+
+impl CellCoord {
+    pub const fn new(x: i32, z: i32) -> Self {
+        Self { x, z }
+    }
+
+    /// Chunk holding the cell
+    pub fn chunk(self, side: i32) -> ChunkCoord {
+        ChunkCoord::new(self.x.div_euclid(side), self.z.div_euclid(side))
+    }
+
+    /// Index of the cell in its chunk's per-cell fields, see `ChunkCoord::cell`
+    pub fn index_in_chunk(self, side: i32) -> usize {
+        (self.x.rem_euclid(side) * side + self.z.rem_euclid(side)) as usize
+    }
+
+    /// Axial hex coordinates of the cell
+    pub fn hex(self) -> Hex {
+        Hex::new(self.x - (self.z - (self.z & 1)) / 2, self.z)
+    }
+
+    /// Cell at axial hex coordinates, the inverse of `hex`
+    pub fn from_hex(hex: Hex) -> Self {
+        Self::new(hex.x + (hex.y - (hex.y & 1)) / 2, hex.y)
+    }
+}
+
+/// Converts between chunk, cell and hex coordinates and world positions
+///
+/// In chunk units a chunk covers the unit square at its coordinates, which
+/// holds the centres of its cells with half a cell to spare on every side.
+#[derive(Clone, Debug)]
+pub struct HexGrid {
+    chunk_side: i32,
+    layout: HexLayout,
+    /// Distance between neighbouring cells along x and between rows along z,
+    /// negative where the layout's axis points the other way
+    cell_step: Vec2,
+    /// World position of the corner of chunk (0, 0)
+    origin: Vec2,
+}
+
+impl Default for HexGrid {
+    fn default() -> Self {
+        Self::new(CHUNK_SIDE, HEX_SIZE)
+    }
+}
+
+impl HexGrid {
+    pub fn new(chunk_side: i32, hex_size: f32) -> Self {
+        let layout = HexLayout::pointy().with_hex_size(hex_size);
+        let center = |x, z| layout.hex_to_world_pos(CellCoord::new(x, z).hex());
+        let first = center(0, 0);
+        // Two rows apart, so the odd row shift cancels out
+        let cell_step = Vec2::new((center(1, 0) - first).x, (center(0, 2) - first).y / 2.0);
+
+        Self {
+            chunk_side,
+            layout,
+            cell_step,
+            origin: first - cell_step / 2.0,
+        }
+    }
+
+    /// Cells along each side of a chunk
+    pub fn chunk_side(&self) -> i32 {
+        self.chunk_side
+    }
+
+    /// Cells in a full chunk
+    pub fn cells_per_chunk(&self) -> usize {
+        (self.chunk_side * self.chunk_side) as usize
+    }
+
+    /// Layout placing the hexes in world space
+    pub fn layout(&self) -> &HexLayout {
+        &self.layout
+    }
+
+    /// World position of a cell's centre
+    pub fn cell_to_world(&self, cell: CellCoord) -> Vec2 {
+        self.layout.hex_to_world_pos(cell.hex())
+    }
+
+    /// Cell whose hex contains a world position
+    pub fn world_to_cell(&self, pos: Vec2) -> CellCoord {
+        CellCoord::from_hex(self.layout.world_pos_to_hex(pos))
+    }
+
+    /// World size of a chunk along x and z, signed like the cell steps
+    pub fn chunk_step(&self) -> Vec2 {
+        self.cell_step * self.chunk_side as f32
+    }
+
+    /// World position of a point given in chunk units
+    pub fn chunk_to_world(&self, chunk_pos: Vec2) -> Vec2 {
+        self.origin + chunk_pos * self.chunk_step()
+    }
+
+    /// Point in chunk units at a world position, the inverse of `chunk_to_world`
+    pub fn world_to_chunk_pos(&self, pos: Vec2) -> Vec2 {
+        (pos - self.origin) / self.chunk_step()
+    }
+
+    /// Chunk covering a world position
+    pub fn world_to_chunk(&self, pos: Vec2) -> ChunkCoord {
+        let chunk_pos = self.world_to_chunk_pos(pos).floor();
+        ChunkCoord::new(chunk_pos.x as i32, chunk_pos.y as i32)
+    }
+
+    /// World area covered by the square of `size` by `size` chunks from `chunk`
+    pub fn chunk_rect(&self, chunk: ChunkCoord, size: i32) -> Rect {
+        let min = Vec2::new(chunk.x as f32, chunk.z as f32);
+        Rect::from_corners(
+            self.chunk_to_world(min),
+            self.chunk_to_world(min + size as f32),
+        )
+    }
+
+    /// World position of a chunk's centre
+    pub fn chunk_center(&self, chunk: ChunkCoord) -> Vec2 {
+        self.chunk_to_world(Vec2::new(chunk.x as f32 + 0.5, chunk.z as f32 + 0.5))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const RANGE: std::ops::Range<i32> = -1_000_000..1_000_000;
+
+    proptest! {
+        #[test]
+        fn cell_hex_round_trip(x in RANGE, z in RANGE) {
+            let cell = CellCoord::new(x, z);
+            prop_assert_eq!(CellCoord::from_hex(cell.hex()), cell);
+
+            let hex = Hex::new(x, z);
+            prop_assert_eq!(CellCoord::from_hex(hex).hex(), hex);
+        }
+
+        #[test]
+        fn cell_chunk_round_trip(x in RANGE, z in RANGE, side in 1..64) {
+            let cell = CellCoord::new(x, z);
+            let index = cell.index_in_chunk(side);
+            prop_assert!(index < (side * side) as usize);
+            prop_assert_eq!(cell.chunk(side).cell(side, index), cell);
+        }
+
+        #[test]
+        fn chunk_cell_round_trip(x in -30_000..30_000, z in -30_000..30_000, side in 1..64, index: usize) {
+            let chunk = ChunkCoord::new(x, z);
+            let index = index % (side * side) as usize;
+            let cell = chunk.cell(side, index);
+            prop_assert_eq!(cell.chunk(side), chunk);
+            prop_assert_eq!(cell.index_in_chunk(side), index);
+        }
+
+        #[test]
+        fn chunk_world_round_trip(
+            x in -10_000..10_000,
+            z in -10_000..10_000,
+            fx in 0.05f32..0.95,
+            fz in 0.05f32..0.95,
+            side in 1..64,
+            hex_size in 1.0f32..50.0,
+        ) {
+            let grid = HexGrid::new(side, hex_size);
+            let chunk = ChunkCoord::new(x, z);
+            let chunk_pos = Vec2::new(x as f32 + fx, z as f32 + fz);
+
+            let world = grid.chunk_to_world(chunk_pos);
+            prop_assert_eq!(grid.world_to_chunk(world), chunk);
+            prop_assert_eq!(grid.world_to_chunk(grid.chunk_center(chunk)), chunk);
+
+            // f32 keeps about 7 significant digits
+            let close = |a: Vec2, b: Vec2| {
+                (a - b).abs().max_element() <= 1e-6 * b.abs().max_element().max(1.0)
+            };
+            let back = grid.world_to_chunk_pos(world);
+            prop_assert!(close(back, chunk_pos), "{back} != {chunk_pos}");
+            let again = grid.chunk_to_world(back);
+            prop_assert!(close(again, world), "{again} != {world}");
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    log_targets::STREAMING,
+    terrain::{
+        dynamic_chunks::{DynamicChunk, SpawnedChunks, despawn_all_chunks},
+        world_config::WorldConfig,
+        world_data::WorldData,
+    },
+};
+
+/// System switching the viewed dimension
+///
+/// `PageUp`/`PageDown` cycle through all loaded dimensions, `Home` jumps back
+/// to the overworld.
+pub fn switch_dimension(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut world_data: ResMut<WorldData>,
+    mut world_config: ResMut<WorldConfig>,
+    mut spawned_chunks: ResMut<SpawnedChunks>,
+    chunk_query: Query<Entity, With<DynamicChunk>>,
+    mut camera_query: Query<&mut Transform, With<Camera2d>>,
+) {
+    let target = if keyboard.just_pressed(KeyCode::PageUp) {
+        world_data.cycle_dimension(1)
+    } else if keyboard.just_pressed(KeyCode::PageDown) {
+        world_data.cycle_dimension(-1)
+    } else if keyboard.just_pressed(KeyCode::Home) {
+        world_data.classifier.overworld_dimension
+    } else {
+        None
+    };
+
+    let Some(target) = target else {
+        return;
+    };
+    if target == world_data.active_dimension {
+        return;
+    }
+
+    world_data.active_dimension = target;
+    // Dimensions may use different chunk sizes
+    world_config.detect_grid(&world_data);
+
+    // Chunks of the previous dimension share coordinates with the new one
+    despawn_all_chunks(&mut commands, &mut spawned_chunks, &chunk_query);
+
+    if let Some(dimension) = world_data.active() {
+        let camera_home = dimension.camera_home();
+        for mut transform in camera_query.iter_mut() {
+            transform.translation = camera_home.extend(transform.translation.z);
+        }
+    }
+
+    log::info!(
+        target: STREAMING,
+        "Switched to dimension {} ({:?})",
+        target,
+        world_data.classifier.classify_dimension(target)
+    );
+}
+
+/// System showing the active dimension in the window title
+pub fn update_dimension_title(
+    world_data: Res<WorldData>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !world_data.is_changed() {
+        return;
+    }
+
+    let Ok(mut window) = window_query.single_mut() else {
+        return;
+    };
+
+    let id = world_data.active_dimension;
+    let title = format!(
+        "bittravel - dimension {} ({:?}, {}/{})",
+        id,
+        world_data.classifier.classify_dimension(id),
+        world_data
+            .dimensions
+            .keys()
+            .position(|&d| d == id)
+            .map_or(0, |i| i + 1),
+        world_data.dimensions.len()
+    );
+    if window.title != title {
+        window.title = title;
+    }
+}
@@ -1,17 +1,32 @@
 use bevy::prelude::*;
-use hexx::HexLayout;
-use std::collections::HashSet;
-
-use crate::terrain::{
-    camera_culling::{ChunkBounds, VisibleChunk},
-    chunk_mesh::create_chunk_mesh,
-    world_data::WorldData,
+use std::{collections::HashSet, sync::Arc};
+
+use crate::{
+    log_targets::STREAMING,
+    terrain::{
+        camera_culling::{ChunkBounds, VisibleChunk, calculate_visible_chunks},
+        chunk_mesh::HexTemplate,
+        contours::Contours,
+        coords::ChunkCoord,
+        hillshade::Hillshade,
+        instanced::Renderer,
+        lod::{Superchunk, group_of, group_size, has_chunks, lod_level},
+        mesh_cache::{MeshCache, MeshKey},
+        mesh_tasks::{ChunkMaterials, MeshJob, MeshSource, insert_chunk_mesh, placeholder_mesh},
+        palette::{ActivePalette, BiomePalette},
+        view_mode::ViewMode,
+        world_config::WorldConfig,
+        world_data::WorldData,
+    },
 };
 
 /// Component to mark dynamically spawned chunk entities
 #[derive(Component)]
 pub struct DynamicChunk {
+    /// Chunk coordinates, or superchunk coordinates above level 0
     pub chunk_coords: (i32, i32),
+    /// Level of detail the entity was meshed at
+    pub level: u8,
 }
 
 /// Resource to track which chunks are currently spawned
@@ -20,33 +35,61 @@ pub struct SpawnedChunks {
     pub chunks: HashSet<(i32, i32)>,
     pub last_camera_pos: Vec2,
     pub last_zoom_scale: f32,
+    /// World size of the view at the last update
+    pub last_view_size: Vec2,
+    /// Level of detail of the spawned chunks
+    pub level: u8,
+    /// Set when chunk data changed and the viewport must be re-evaluated
+    pub needs_refresh: bool,
 }
 
 /// System that dynamically spawns/despawns chunks based on camera viewport
+///
+/// The map camera drives streaming in both view modes, the 3D camera orbits
+/// the point it looks at.
+#[allow(clippy::too_many_arguments)]
 pub fn update_dynamic_chunks(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    chunk_materials: Res<ChunkMaterials>,
+    mut mesh_cache: ResMut<MeshCache>,
     world_data: Res<WorldData>,
+    world_config: Res<WorldConfig>,
+    active_palette: Res<ActivePalette>,
+    palettes: Res<Assets<BiomePalette>>,
+    hillshade: Res<Hillshade>,
+    contours: Res<Contours>,
+    view_mode: Res<ViewMode>,
+    renderer: Res<Renderer>,
     mut spawned_chunks: ResMut<SpawnedChunks>,
-    camera_query: Query<(&Transform, &Projection), With<Camera>>,
+    camera_query: Query<(&Transform, &Projection), With<Camera2d>>,
     chunk_query: Query<(Entity, &DynamicChunk)>,
 ) {
     let Ok((camera_transform, projection)) = camera_query.single() else {
         return;
     };
 
-    let camera_pos = camera_transform.translation.truncate();
+    let Some(dimension) = world_data.active() else {
+        return;
+    };
 
-    // Get current zoom and calculate actual viewport bounds
-    let current_zoom = if let Projection::Orthographic(ref ortho) = *projection {
-        ortho.scale
-    } else {
-        1.0
+    // Wait for the palette, its first load triggers the initial spawn
+    let Some(palette) = palettes.get(active_palette.handle()) else {
+        return;
     };
 
+    let Projection::Orthographic(ortho) = projection else {
+        return; // Only orthographic map cameras stream chunks
+    };
+
+    let camera_pos = camera_transform.translation.truncate();
+    let current_zoom = ortho.scale;
+
+    // The projection area follows the window, so resizes change it too
+    let view_size = ortho.area.size();
+
     // Check if camera moved OR zoom changed significantly
-    let movement_threshold = 100.0; // Prevent flickering - ~25% of chunk size
+    let movement_threshold = world_config.refresh_distance;
     let zoom_threshold = 0.05; // Less sensitive to zoom changes
 
     // Handle first run - initialize tracking values
@@ -65,76 +108,55 @@ pub fn update_dynamic_chunks(
         (spawned_chunks.last_zoom_scale - current_zoom).abs() > zoom_threshold
     };
 
-    if !camera_moved && !zoom_changed {
+    let view_resized = (spawned_chunks.last_view_size - view_size)
+        .abs()
+        .max_element()
+        > movement_threshold;
+
+    if !camera_moved && !zoom_changed && !view_resized && !spawned_chunks.needs_refresh {
         return;
     }
 
     // Update tracking values
     spawned_chunks.last_camera_pos = camera_pos;
     spawned_chunks.last_zoom_scale = current_zoom;
+    spawned_chunks.last_view_size = view_size;
+    spawned_chunks.needs_refresh = false;
 
-    // Calculate viewport bounds in world coordinates
-    let window_size = 1000.0; // Window is 1000x1000
-
-    // Balanced scale factor for proper viewport coverage without memory explosion
-    let scale_factor = 25.0; // Target ~400-800 chunks for good coverage
-    let world_viewport_half_width = window_size * current_zoom * scale_factor * 0.5;
-    let world_viewport_half_height = window_size * current_zoom * scale_factor * 0.5;
+    // Visible world rectangle, which need not be square
+    let view = calculate_visible_chunks(camera_transform, ortho);
 
-    log::info!(
-        "Using scale factor: {} for balanced viewport coverage",
-        scale_factor
-    );
-
-    // Camera viewport bounds in world coordinates
-    let viewport_min_x = camera_pos.x - world_viewport_half_width;
-    let viewport_max_x = camera_pos.x + world_viewport_half_width;
-    let viewport_min_y = camera_pos.y - world_viewport_half_height;
-    let viewport_max_y = camera_pos.y + world_viewport_half_height;
-
-    // Convert world bounds to chunk coordinates
-    let chunk_size = 32.0 * 13.0; // 416 world units per chunk
-
-    // DIAGNOSTIC LOGGING
-    log::info!("=== VIEWPORT CALCULATION DEBUG ===");
-    log::info!("Camera pos: ({:.1}, {:.1})", camera_pos.x, camera_pos.y);
-    log::info!("Current zoom: {:.3}", current_zoom);
-    log::info!(
-        "Center offset: ({:.1}, {:.1})",
-        world_data.center_offset.x,
-        world_data.center_offset.y
-    );
-    log::info!(
-        "Viewport half size: {:.1} x {:.1}",
-        world_viewport_half_width,
-        world_viewport_half_height
-    );
-    log::info!(
-        "Viewport bounds: x[{:.1}, {:.1}] y[{:.1}, {:.1}]",
-        viewport_min_x,
-        viewport_max_x,
-        viewport_min_y,
-        viewport_max_y
+    log::debug!(
+        target: STREAMING,
+        "Viewport: camera ({:.1}, {:.1}), zoom {:.3}, size {:.0}x{:.0}, bounds x[{:.1}, {:.1}] y[{:.1}, {:.1}]",
+        camera_pos.x,
+        camera_pos.y,
+        current_zoom,
+        view_size.x,
+        view_size.y,
+        view.min.x,
+        view.max.x,
+        view.min.y,
+        view.max.y
     );
 
-    // Add padding for smooth loading
-    let chunk_padding = 3;
-    log::info!("Chunk size: {:.1}, padding: {}", chunk_size, chunk_padding);
+    // Add the margin for smooth loading
+    let chunk_padding = world_config.chunk_padding;
 
-    // Convert viewport bounds directly to chunk coordinates
-    // Camera is already positioned relative to center_offset, so no additional offset needed
-    let min_chunk_x_raw = (viewport_min_x / chunk_size).floor() as i32;
-    let max_chunk_x_raw = (viewport_max_x / chunk_size).ceil() as i32;
-    let min_chunk_z_raw = (viewport_min_y / chunk_size).floor() as i32;
-    let max_chunk_z_raw = (viewport_max_y / chunk_size).ceil() as i32;
+    // Convert viewport corners to chunk coordinates. Meshes are drawn
+    // shifted by the centre offset, so add it back to get grid positions
+    let grid = &world_config.grid;
+    let corner_a = grid.world_to_chunk(view.min + dimension.center_offset);
+    let corner_b = grid.world_to_chunk(view.max + dimension.center_offset);
 
     // Apply padding
-    let min_chunk_x = min_chunk_x_raw - chunk_padding;
-    let max_chunk_x = max_chunk_x_raw + chunk_padding;
-    let min_chunk_z = min_chunk_z_raw - chunk_padding;
-    let max_chunk_z = max_chunk_z_raw + chunk_padding;
+    let min_chunk_x = corner_a.x.min(corner_b.x) - chunk_padding;
+    let max_chunk_x = corner_a.x.max(corner_b.x) + chunk_padding;
+    let min_chunk_z = corner_a.z.min(corner_b.z) - chunk_padding;
+    let max_chunk_z = corner_a.z.max(corner_b.z) + chunk_padding;
 
     log::debug!(
+        target: STREAMING,
         "Viewport -> chunks: x[{}, {}] z[{}, {}]",
         min_chunk_x,
         max_chunk_x,
@@ -142,83 +164,167 @@ pub fn update_dynamic_chunks(
         max_chunk_z
     );
 
-    // Convert viewport chunk coordinates to world chunk coordinates
-    // Camera viewport chunks are relative to camera center, need to map to world coordinates [0, 239]
-    let center_chunk_x = (world_data.center_offset.x / chunk_size) as i32;
-    let center_chunk_z = (world_data.center_offset.y / chunk_size) as i32;
-
-    let world_min_x = (min_chunk_x + center_chunk_x).max(0);
-    let world_max_x = (max_chunk_x + center_chunk_x).min(239);
-    let world_min_z = (min_chunk_z + center_chunk_z).max(0);
-    let world_max_z = (max_chunk_z + center_chunk_z).min(239);
+    // Keep to the chunks the dimension actually has
+    let (bounds_min_x, bounds_max_x, bounds_min_z, bounds_max_z) = dimension.bounds;
+    let world_min_x = min_chunk_x.max(bounds_min_x);
+    let world_max_x = max_chunk_x.min(bounds_max_x);
+    let world_min_z = min_chunk_z.max(bounds_min_z);
+    let world_max_z = max_chunk_z.min(bounds_max_z);
+
+    // Past the zoom thresholds whole groups of chunks are drawn as one
+    let level = lod_level(current_zoom);
+    if level != spawned_chunks.level {
+        spawned_chunks.chunks.clear();
+        spawned_chunks.level = level;
+    }
+    let (group_min_x, group_min_z) = group_of((world_min_x, world_min_z), level);
+    let (group_max_x, group_max_z) = group_of((world_max_x, world_max_z), level);
 
-    // Collect chunks in viewport
+    // Collect chunks in viewport, at level 0 each group is a single chunk
     let mut chunks_in_viewport = HashSet::new();
-    for chunk_x in world_min_x..=world_max_x {
-        for chunk_z in world_min_z..=world_max_z {
-            if world_data.chunks.contains_key(&(chunk_x, chunk_z)) {
+    for chunk_x in group_min_x..=group_max_x {
+        for chunk_z in group_min_z..=group_max_z {
+            if has_chunks(dimension, level, (chunk_x, chunk_z)) {
                 chunks_in_viewport.insert((chunk_x, chunk_z));
             }
         }
     }
 
     log::debug!(
-        "Viewport culling: {} chunks in view",
-        chunks_in_viewport.len()
+        target: STREAMING,
+        "Viewport culling: {} chunks in view at level {}",
+        chunks_in_viewport.len(),
+        level
     );
 
     // Despawn chunks that are no longer in viewport
     let mut despawned_count = 0;
     for (entity, dynamic_chunk) in chunk_query.iter() {
-        if !chunks_in_viewport.contains(&dynamic_chunk.chunk_coords) {
+        if dynamic_chunk.level != level {
+            commands.entity(entity).despawn();
+            despawned_count += 1;
+        } else if !chunks_in_viewport.contains(&dynamic_chunk.chunk_coords) {
             commands.entity(entity).despawn();
             spawned_chunks.chunks.remove(&dynamic_chunk.chunk_coords);
             despawned_count += 1;
         }
     }
 
-    // Spawn new chunks that came into viewport
-    let layout = HexLayout::pointy().with_hex_size(13.0);
+    // Spawn new chunks that came into viewport. Cached meshes are reused,
+    // other chunks show placeholder tiles until they are meshed off the main
+    // thread
+    let template = Arc::new(HexTemplate::new(grid.layout()));
+    let mut shared_palette = None;
     let mut spawned_count = 0;
+    let mut cached_count = 0;
 
     for &chunk_coords in &chunks_in_viewport {
         if !spawned_chunks.chunks.contains(&chunk_coords) {
-            // Get chunk data
-            let Some(chunk) = world_data.chunks.get(&chunk_coords) else {
-                continue;
-            };
+            // Superchunks are meshed at chunk scale and the entity scaled up
+            let size = group_size(level);
+            let scale = size as f32;
 
             // Calculate chunk bounds for culling
-            let chunk_center_x = chunk.chunk_x as f32 * chunk_size;
-            let chunk_center_z = chunk.chunk_z as f32 * chunk_size;
-
+            let first_chunk = ChunkCoord::new(chunk_coords.0 * size, chunk_coords.1 * size);
+            let group_rect = grid.chunk_rect(first_chunk, size);
             let chunk_bounds = ChunkBounds {
                 chunk_coords,
-                world_bounds: Rect::new(
-                    chunk_center_x - world_data.center_offset.x - chunk_size / 2.0,
-                    chunk_center_z - world_data.center_offset.y - chunk_size / 2.0,
-                    chunk_size,
-                    chunk_size,
+                world_bounds: Rect::from_corners(
+                    group_rect.min - dimension.center_offset,
+                    group_rect.max - dimension.center_offset,
                 ),
             };
 
-            // Generate combined mesh for chunk
-            let (chunk_mesh, chunk_component) =
-                create_chunk_mesh(chunk, &layout, world_data.center_offset);
-            let mesh_handle = meshes.add(chunk_mesh);
-
-            // Use white material to allow vertex colors to show through
-            let material = materials.add(ColorMaterial::from(Color::WHITE));
+            // Instanced chunks are colored and shaded on the GPU, batched
+            // chunks bake shading and contours in at full detail only
+            let instanced = renderer.recolors_on_gpu(*view_mode);
+            let overlays = if level == 0 && *view_mode == ViewMode::Map2d && !instanced {
+                (hillshade.enabled, contours.enabled)
+            } else {
+                (false, false)
+            };
+            let key = MeshKey {
+                dimension: world_data.active_dimension,
+                chunk_coords,
+                level,
+                view_mode: *view_mode,
+                palette_version: (!instanced).then(|| active_palette.version()),
+                overlays,
+            };
 
-            commands.spawn((
-                Mesh2d(mesh_handle),
-                MeshMaterial2d(material),
-                Transform::IDENTITY,
-                chunk_component,
+            let world_bounds = chunk_bounds.world_bounds;
+            let chunk_components = (
+                Transform::from_scale(Vec3::new(scale, scale, 1.0)),
                 chunk_bounds,
                 VisibleChunk,
-                DynamicChunk { chunk_coords },
-            ));
+                DynamicChunk {
+                    chunk_coords,
+                    level,
+                },
+            );
+
+            if let Some(cached) = mesh_cache.get(&key) {
+                let mut chunk_entity = commands.spawn(chunk_components);
+                insert_chunk_mesh(
+                    &mut chunk_entity,
+                    &cached,
+                    *view_mode,
+                    &chunk_materials,
+                    contours.enabled,
+                );
+                spawned_chunks.chunks.insert(chunk_coords);
+                spawned_count += 1;
+                cached_count += 1;
+                continue;
+            }
+
+            // Only slots are collected here, the task decodes them
+            let source = if level > 0 {
+                MeshSource::Superchunk {
+                    level,
+                    members: Superchunk::members(dimension, level, chunk_coords),
+                }
+            } else {
+                let Some(chunk) = dimension.chunks.get(&chunk_coords) else {
+                    continue;
+                };
+
+                // Shading and contours need the neighbours' edges
+                let neighbors = (instanced || overlays.0 || overlays.1)
+                    .then(|| dimension.slots_around(chunk_coords));
+                MeshSource::Chunk {
+                    chunk: chunk.clone(),
+                    neighbors,
+                }
+            };
+
+            let pending = MeshJob {
+                source,
+                chunk_coords,
+                template: template.clone(),
+                center_offset: dimension.center_offset / scale,
+                palette: shared_palette
+                    .get_or_insert_with(|| Arc::new(palette.clone()))
+                    .clone(),
+                hillshade: *hillshade,
+                contours: *contours,
+                view_mode: *view_mode,
+                renderer: *renderer,
+            }
+            .spawn(key);
+
+            let placeholder = meshes.add(placeholder_mesh(world_bounds, scale));
+            let mut chunk_entity = commands.spawn((chunk_components, pending));
+            match *view_mode {
+                ViewMode::Map2d => chunk_entity.insert((
+                    Mesh2d(placeholder),
+                    MeshMaterial2d(chunk_materials.placeholder.clone()),
+                )),
+                ViewMode::Terrain3d => chunk_entity.insert((
+                    Mesh3d(placeholder),
+                    MeshMaterial3d(chunk_materials.placeholder_3d.clone()),
+                )),
+            };
 
             spawned_chunks.chunks.insert(chunk_coords);
             spawned_count += 1;
@@ -227,10 +333,25 @@ pub fn update_dynamic_chunks(
 
     if spawned_count > 0 || despawned_count > 0 {
         log::debug!(
-            "Viewport update: spawned {} chunks, despawned {} chunks, total visible: {}",
+            target: STREAMING,
+            "Viewport update: spawned {} chunks ({} from cache), despawned {} chunks, total visible: {}",
             spawned_count,
+            cached_count,
             despawned_count,
             chunks_in_viewport.len()
         );
     }
 }
+
+/// Despawn every chunk entity so the next update spawns them again
+pub fn despawn_all_chunks(
+    commands: &mut Commands,
+    spawned_chunks: &mut SpawnedChunks,
+    chunk_query: &Query<Entity, With<DynamicChunk>>,
+) {
+    for entity in chunk_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    spawned_chunks.chunks.clear();
+    spawned_chunks.needs_refresh = true;
+}
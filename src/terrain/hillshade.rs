@@ -0,0 +1,281 @@
+use bevy::prelude::*;
+use hexx::{Hex, HexLayout};
+use std::{borrow::Cow, collections::HashMap};
+
+use crate::{
+    cli::ViewerOptions,
+    log_targets::RENDERING,
+    terrain::{
+        cell::Cell,
+        chunk::TerrainChunkState,
+        coords::CellCoord,
+        dynamic_chunks::{DynamicChunk, SpawnedChunks, despawn_all_chunks},
+        instanced::Renderer,
+        view_mode::ViewMode,
+        world_data::ChunkSlot,
+    },
+};
+
+/// Relief lighting settings, toggled with `H`
+#[derive(Resource, Clone, Copy, PartialEq, Debug)]
+pub struct Hillshade {
+    pub enabled: bool,
+    /// Direction the sun shines from, in degrees clockwise from north (up)
+    pub azimuth: f32,
+    /// Height of the sun above the horizon, in degrees
+    pub altitude: f32,
+    /// How much shading darkens or brightens the base color, 0 to 1
+    pub strength: f32,
+    /// Vertical exaggeration, elevation units per hex step
+    pub exaggeration: f32,
+}
+
+impl Default for Hillshade {
+    fn default() -> Self {
+        // Light from the north-west, the usual cartographic convention
+        Self {
+            enabled: false,
+            azimuth: 315.0,
+            altitude: 45.0,
+            strength: 0.6,
+            exaggeration: 2.0,
+        }
+    }
+}
+
+impl Hillshade {
+    /// Settings requested on the command line
+    pub fn from_options(options: &ViewerOptions) -> Self {
+        let (azimuth, altitude) = options.sun;
+        Self {
+            enabled: options.hillshade,
+            azimuth,
+            altitude,
+            ..default()
+        }
+    }
+
+    /// Brightness factor of a cell, 1 for flat ground
+    ///
+    /// The slope comes from the cell's six neighbours, neighbours that are
+    /// not loaded count as level with the cell.
+    pub fn shade(&self, heights: &Heightfield, layout: &HexLayout, cell: &Cell) -> f32 {
+        let center = cell.hex();
+        let Some(height) = heights.height(center) else {
+            return 1.0;
+        };
+
+        // Least-squares gradient over six evenly spaced directions
+        let origin = layout.hex_to_world_pos(Hex::ZERO);
+        let mut gradient = Vec2::ZERO;
+        for offset in Hex::NEIGHBORS_COORDS {
+            let neighbor_height = heights.height(center + offset).unwrap_or(height);
+            let direction = (layout.hex_to_world_pos(offset) - origin).normalize();
+            gradient += direction * (neighbor_height as f32 - height as f32);
+        }
+        gradient *= self.exaggeration / 3.0;
+
+        let normal = Vec3::new(-gradient.x, -gradient.y, 1.0).normalize();
+        let azimuth = self.azimuth.to_radians();
+        let altitude = self.altitude.to_radians();
+        let sun = Vec3::new(
+            altitude.cos() * azimuth.sin(),
+            altitude.cos() * azimuth.cos(),
+            altitude.sin(),
+        );
+
+        // Scale so flat ground keeps its palette color
+        let lit = normal.dot(sun).max(0.0) / altitude.sin().max(f32::EPSILON);
+        (1.0 - self.strength + self.strength * lit).clamp(0.0, 2.0)
+    }
+}
+
+/// Heights of a chunk and the chunks around it
+///
+/// For shading, water surfaces count as flat at their water level, so lakes
+/// and rivers are not shaded by the ground beneath them.
+pub struct Heightfield<'a> {
+    chunks: HashMap<(i32, i32), Cow<'a, TerrainChunkState>>,
+    side_len: i32,
+}
+
+impl<'a> Heightfield<'a> {
+    /// Heights for shading `chunk`, including the cells across its borders
+    ///
+    /// `neighbors` are the slots of the chunks around it, decoded here so the
+    /// heights can be assembled off the main thread.
+    pub fn around(chunk: &'a TerrainChunkState, neighbors: &'a [((i32, i32), ChunkSlot)]) -> Self {
+        let mut chunks: HashMap<_, _> = neighbors
+            .iter()
+            .filter_map(|(coords, slot)| Some((*coords, slot.load()?)))
+            .collect();
+        chunks.insert((chunk.chunk_x, chunk.chunk_z), Cow::Borrowed(chunk));
+
+        Self {
+            chunks,
+            side_len: chunk.side_len().unwrap_or_default() as i32,
+        }
+    }
+
+    /// Surface height of a cell, `None` when its chunk is not loaded
+    pub fn height(&self, cell: Hex) -> Option<i16> {
+        let (chunk, index) = self.locate(cell)?;
+        let elevation = *chunk.elevations.get(index)?;
+        let water_level = chunk.water_levels.get(index).copied().unwrap_or(elevation);
+        Some(elevation.max(water_level))
+    }
+
+    /// Ground elevation of a cell, ignoring water
+    pub fn elevation(&self, cell: Hex) -> Option<i16> {
+        let (chunk, index) = self.locate(cell)?;
+        chunk.elevations.get(index).copied()
+    }
+
+    /// Chunk holding a cell and the cell's index in it
+    fn locate(&self, cell: Hex) -> Option<(&TerrainChunkState, usize)> {
+        if self.side_len == 0 {
+            return None;
+        }
+
+        let cell = CellCoord::from_hex(cell);
+        let chunk = self.chunks.get(&cell.chunk(self.side_len).into())?;
+        Some((chunk, cell.index_in_chunk(self.side_len)))
+    }
+}
+
+/// System toggling hillshading with `H`
+pub fn toggle_hillshade(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut hillshade: ResMut<Hillshade>,
+    renderer: Res<Renderer>,
+    view_mode: Res<ViewMode>,
+    mut spawned_chunks: ResMut<SpawnedChunks>,
+    chunk_query: Query<Entity, With<DynamicChunk>>,
+) {
+    if !keyboard.just_pressed(KeyCode::KeyH) {
+        return;
+    }
+
+    hillshade.enabled = !hillshade.enabled;
+    if !renderer.recolors_on_gpu(*view_mode) {
+        despawn_all_chunks(&mut commands, &mut spawned_chunks, &chunk_query);
+    }
+
+    log::info!(
+        target: RENDERING,
+        "Hillshading {}",
+        if hillshade.enabled { "on" } else { "off" }
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    const SIDE: usize = 8;
+
+    fn layout() -> HexLayout {
+        HexLayout::pointy().with_hex_size(1.0)
+    }
+
+    /// Chunk at (chunk_x, 0) with the elevation of each cell given by its world position
+    fn chunk(chunk_x: i32, elevation: impl Fn(Vec2) -> i16) -> TerrainChunkState {
+        let mut chunk = TerrainChunkState {
+            chunk_index: chunk_x as u64,
+            chunk_x,
+            chunk_z: 0,
+            dimension: 1,
+            biomes: vec![1; SIDE * SIDE],
+            biome_density: vec![0; SIDE * SIDE],
+            elevations: vec![0; SIDE * SIDE],
+            water_levels: vec![0; SIDE * SIDE],
+            water_body_types: vec![0; SIDE * SIDE],
+            zoning_types: vec![0; SIDE * SIDE],
+            original_elevations: vec![0; SIDE * SIDE],
+        };
+        for (index, cell) in chunk.cells().into_iter().enumerate() {
+            chunk.elevations[index] = elevation(layout().hex_to_world_pos(cell.hex()));
+        }
+        chunk
+    }
+
+    /// Ground falling away towards `facing`, a direction in the map plane,
+    /// high enough to stay above the chunk's water level of 0
+    fn slope(facing: Vec2) -> impl Fn(Vec2) -> i16 {
+        move |pos| 100 - (pos.dot(facing) * 2.0).round() as i16
+    }
+
+    fn shade_at(
+        hillshade: &Hillshade,
+        chunk: &TerrainChunkState,
+        neighbors: &[((i32, i32), ChunkSlot)],
+        index: usize,
+    ) -> f32 {
+        let heights = Heightfield::around(chunk, neighbors);
+        hillshade.shade(&heights, &layout(), &chunk.cells()[index])
+    }
+
+    /// A cell in the middle of the chunk, with all six neighbours in it
+    const MIDDLE: usize = 4 * SIDE + 4;
+
+    #[test]
+    fn flat_ground_keeps_its_color() {
+        let flat = chunk(0, |_| 30);
+        let shade = shade_at(&Hillshade::default(), &flat, &[], MIDDLE);
+        assert!((shade - 1.0).abs() < 1e-6, "{shade}");
+    }
+
+    #[test]
+    fn slopes_facing_the_sun_are_brighter() {
+        // Azimuth is clockwise from north, which is +y on the map
+        let north_west = Vec2::new(-1.0, 1.0).normalize();
+        let hillshade = Hillshade::default();
+        assert_eq!(hillshade.azimuth, 315.0);
+
+        let lit = shade_at(&hillshade, &chunk(0, slope(north_west)), &[], MIDDLE);
+        let shaded = shade_at(&hillshade, &chunk(0, slope(-north_west)), &[], MIDDLE);
+        assert!(lit > 1.0, "{lit}");
+        assert!(shaded < 1.0, "{shaded}");
+
+        // A sun in the east lights east-facing slopes
+        let east = Hillshade {
+            azimuth: 90.0,
+            ..default()
+        };
+        let lit = shade_at(&east, &chunk(0, slope(Vec2::X)), &[], MIDDLE);
+        let shaded = shade_at(&east, &chunk(0, slope(Vec2::NEG_X)), &[], MIDDLE);
+        assert!(lit > 1.0 && shaded < 1.0, "{lit} {shaded}");
+    }
+
+    #[test]
+    fn unloaded_neighbours_count_as_level() {
+        let hillshade = Hillshade::default();
+        let plateau = chunk(0, |_| 50);
+        // Cell (0, 0) borders the unloaded chunks at x = -1
+        let shade = shade_at(&hillshade, &plateau, &[], 0);
+        assert!((shade - 1.0).abs() < 1e-6, "{shade}");
+
+        // A loaded neighbour across the border does shade it
+        let lowland = ChunkSlot::Decoded(Arc::new(chunk(-1, |_| 0)));
+        let shade = shade_at(&hillshade, &plateau, &[((-1, 0), lowland)], 0);
+        assert!((shade - 1.0).abs() > 0.01, "{shade}");
+    }
+
+    #[test]
+    fn water_surfaces_are_flat() {
+        let mut lake = chunk(0, slope(Vec2::X));
+        lake.water_levels = vec![200; SIDE * SIDE];
+        let cell = lake.cells()[MIDDLE].hex();
+        let ground = lake.elevations[MIDDLE];
+
+        let heights = Heightfield::around(&lake, &[]);
+        assert_eq!(heights.height(cell), Some(200));
+        assert_eq!(heights.elevation(cell), Some(ground));
+        assert_eq!(heights.height(cell + Hex::new(-(SIDE as i32), 0)), None);
+
+        let shade = shade_at(&Hillshade::default(), &lake, &[], MIDDLE);
+        assert!((shade - 1.0).abs() < 1e-6, "{shade}");
+    }
+}
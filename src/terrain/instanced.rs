@@ -0,0 +1,470 @@
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, MeshVertexAttribute, MeshVertexBufferLayoutRef},
+        render_asset::RenderAssetUsages,
+        render_resource::{
+            AsBindGroup, PrimitiveTopology, RenderPipelineDescriptor, ShaderRef,
+            SpecializedMeshPipelineError, VertexFormat,
+        },
+        storage::ShaderStorageBuffer,
+    },
+    sprite::{Material2d, Material2dKey},
+};
+use hexx::HexLayout;
+use std::collections::HashMap;
+
+use crate::{
+    log_targets::RENDERING,
+    terrain::{
+        biome::BiomeInfo,
+        biome_colors_range::ColorRange,
+        cell::Cell,
+        chunk_mesh::{ChunkMesh, HexTemplate},
+        contours::{ContourLayer, Contours},
+        coords::HexGrid,
+        hillshade::{Heightfield, Hillshade},
+        palette::{ActivePalette, BiomePalette},
+        view_mode::ViewMode,
+        water::WaterBodyType,
+        world_config::WorldConfig,
+    },
+};
+
+/// Shader drawing instanced hexes, relative to the `assets` directory
+pub const HEX_INSTANCES_SHADER: &str = "shaders/hex_instances.wgsl";
+
+/// Index of the cell a vertex belongs to, in the chunk's cell buffer
+pub const ATTRIBUTE_CELL_INDEX: MeshVertexAttribute =
+    MeshVertexAttribute::new("Hex_CellIndex", 402_781_633, VertexFormat::Uint32);
+
+/// Water bodies in water color table order, after the default water range
+const WATER_BODIES: [WaterBodyType; 4] = [
+    WaterBodyType::Ocean,
+    WaterBodyType::Lake,
+    WaterBodyType::River,
+    WaterBodyType::Swamp,
+];
+
+/// How the 2D map is drawn, chosen with `--renderer`
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Renderer {
+    /// Chunk meshes with the colors baked into their vertices
+    #[default]
+    Batched,
+    /// One shared hex mesh per chunk, colored on the GPU from cell buffers
+    Instanced,
+}
+
+impl Renderer {
+    /// Whether palette changes and overlay toggles apply to the spawned
+    /// chunks without rebuilding them
+    pub fn recolors_on_gpu(self, view_mode: ViewMode) -> bool {
+        self == Renderer::Instanced && view_mode == ViewMode::Map2d
+    }
+}
+
+pub use gpu::{GpuBiomeColors, GpuCell, GpuColorRange, HexParams};
+
+/// Buffer layouts shared with `hex_instances.wgsl`
+// The `ShaderType` derive emits a `check` fn per field, which rustc reports as
+// never used
+#[allow(dead_code)]
+mod gpu {
+    use bevy::{prelude::*, render::render_resource::ShaderType};
+
+    /// Per-cell data read by the hex shader
+    ///
+    /// Everything here is independent of the palette and of the overlay
+    /// toggles, so neither requires rebuilding it.
+    #[derive(ShaderType, Clone, Copy, Default, Debug)]
+    pub struct GpuCell {
+        /// Hex centre relative to the chunk entity
+        pub center: Vec2,
+        /// Index into the biome color table
+        pub biome: u32,
+        /// How much the bordering biome's color is mixed in
+        pub secondary_weight: f32,
+        /// Elevation scaled to 0-1 along the color range
+        pub elevation: f32,
+        /// Index into the water color table, -1 for dry cells
+        pub water_body: i32,
+        /// Water depth scaled to 0-1 along the water range
+        pub water_depth: f32,
+        /// Hillshade brightness, applied while hillshading is on
+        pub shade: f32,
+        /// 0 for padding cells of partial chunks, which are not drawn
+        pub visible: u32,
+    }
+
+    /// Color range in linear RGB
+    #[derive(ShaderType, Clone, Copy, Default, Debug)]
+    pub struct GpuColorRange {
+        pub start: Vec4,
+        pub end: Vec4,
+    }
+
+    /// Ranges a packed biome value is colored with
+    #[derive(ShaderType, Clone, Copy, Default, Debug)]
+    pub struct GpuBiomeColors {
+        pub own: GpuColorRange,
+        pub secondary: GpuColorRange,
+        /// 1 when the cell borders another biome
+        pub has_secondary: u32,
+    }
+
+    /// Overlay settings shared by every instanced chunk
+    #[derive(ShaderType, Clone, Copy, Default, Debug)]
+    pub struct HexParams {
+        /// 1 while hillshading is on
+        pub hillshade: u32,
+    }
+}
+
+impl From<ColorRange> for GpuColorRange {
+    fn from(range: ColorRange) -> Self {
+        Self {
+            start: range.start.to_linear().to_vec4(),
+            end: range.end.to_linear().to_vec4(),
+        }
+    }
+}
+
+/// Material coloring a chunk's hexes from its cell buffer
+///
+/// The color tables are shared by all chunks and rewritten when the palette
+/// changes.
+#[derive(Asset, TypePath, AsBindGroup, Clone, Debug)]
+pub struct HexInstanceMaterial {
+    #[storage(0, read_only)]
+    pub cells: Handle<ShaderStorageBuffer>,
+    #[storage(1, read_only)]
+    pub biome_colors: Handle<ShaderStorageBuffer>,
+    #[storage(2, read_only)]
+    pub water_colors: Handle<ShaderStorageBuffer>,
+    #[uniform(3)]
+    pub params: HexParams,
+}
+
+impl Material2d for HexInstanceMaterial {
+    fn vertex_shader() -> ShaderRef {
+        HEX_INSTANCES_SHADER.into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        HEX_INSTANCES_SHADER.into()
+    }
+
+    fn specialize(
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayoutRef,
+        _key: Material2dKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let vertex_layout = layout.0.get_layout(&[
+            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+            ATTRIBUTE_CELL_INDEX.at_shader_location(1),
+        ])?;
+        descriptor.vertex.buffers = vec![vertex_layout];
+        Ok(())
+    }
+}
+
+/// Cells of one chunk ready for upload
+pub struct InstancedCells {
+    pub cells: Vec<GpuCell>,
+    /// Packed biome of each cell, turned into table indices on upload
+    pub biomes: Vec<u32>,
+}
+
+impl InstancedCells {
+    /// Pad with hidden cells, or cut, to the hex count of the shared mesh
+    pub fn fit(&mut self, cell_count: usize) {
+        self.cells.resize(cell_count, GpuCell::default());
+        self.biomes.resize(cell_count, 0);
+    }
+}
+
+/// Resource with the shared hex mesh and color tables of the instanced
+/// renderer
+#[derive(Resource)]
+pub struct HexInstancing {
+    /// One chunk's worth of hexes around the origin, moved to their cells
+    /// by the shader
+    pub mesh: Handle<Mesh>,
+    cells_per_chunk: usize,
+    pub biome_colors: Handle<ShaderStorageBuffer>,
+    pub water_colors: Handle<ShaderStorageBuffer>,
+    /// Packed biome values in table order
+    biome_values: Vec<u32>,
+    biome_index: HashMap<u32, u32>,
+    /// Set when the color tables must be rebuilt
+    dirty: bool,
+}
+
+impl FromWorld for HexInstancing {
+    fn from_world(world: &mut World) -> Self {
+        let grid = world
+            .get_resource::<WorldConfig>()
+            .map_or_else(HexGrid::default, |config| config.grid.clone());
+        let cells_per_chunk = grid.cells_per_chunk();
+        let mesh = world
+            .resource_mut::<Assets<Mesh>>()
+            .add(create_instance_mesh(
+                &HexTemplate::new(grid.layout()),
+                cells_per_chunk,
+            ));
+
+        // Storage buffers cannot be empty, the tables are filled once the
+        // palette has loaded
+        let mut buffers = world.resource_mut::<Assets<ShaderStorageBuffer>>();
+        let biome_colors = buffers.add(ShaderStorageBuffer::from(vec![GpuBiomeColors::default()]));
+        let water_colors = buffers.add(ShaderStorageBuffer::from(vec![
+            GpuColorRange::default();
+            1 + WATER_BODIES.len()
+        ]));
+
+        Self {
+            mesh,
+            cells_per_chunk,
+            biome_colors,
+            water_colors,
+            biome_values: Vec::new(),
+            biome_index: HashMap::new(),
+            dirty: true,
+        }
+    }
+}
+
+impl HexInstancing {
+    /// Hexes in the shared mesh, cell buffers are fitted to this count
+    pub fn cells_per_chunk(&self) -> usize {
+        self.cells_per_chunk
+    }
+
+    /// Table index of a packed biome value, adding it when first seen
+    pub fn biome_index(&mut self, biome: u32) -> u32 {
+        if let Some(&index) = self.biome_index.get(&biome) {
+            return index;
+        }
+
+        let index = self.biome_values.len() as u32;
+        self.biome_values.push(biome);
+        self.biome_index.insert(biome, index);
+        self.dirty = true;
+        index
+    }
+
+    /// Material drawing an uploaded cell buffer
+    pub fn material(
+        &self,
+        cells: Handle<ShaderStorageBuffer>,
+        hillshade: &Hillshade,
+    ) -> HexInstanceMaterial {
+        HexInstanceMaterial {
+            cells,
+            biome_colors: self.biome_colors.clone(),
+            water_colors: self.water_colors.clone(),
+            params: HexParams {
+                hillshade: hillshade.enabled as u32,
+            },
+        }
+    }
+
+    /// Color tables of every known biome value for a palette
+    fn color_tables(&self, palette: &BiomePalette) -> (Vec<GpuBiomeColors>, Vec<GpuColorRange>) {
+        let mut biome_colors: Vec<_> = self
+            .biome_values
+            .iter()
+            .map(|&biome| {
+                let secondary = BiomeInfo::try_from(biome)
+                    .ok()
+                    .and_then(|info| info.secondary);
+                GpuBiomeColors {
+                    own: palette.color_range(biome).into(),
+                    secondary: secondary
+                        .map(|secondary| palette.biome_range(secondary).into())
+                        .unwrap_or_default(),
+                    has_secondary: secondary.is_some() as u32,
+                }
+            })
+            .collect();
+        if biome_colors.is_empty() {
+            biome_colors.push(GpuBiomeColors::default());
+        }
+
+        let water_colors = std::iter::once(palette.water_range(None))
+            .chain(
+                WATER_BODIES
+                    .iter()
+                    .map(|&body| palette.water_range(Some(body))),
+            )
+            .map(GpuColorRange::from)
+            .collect();
+
+        (biome_colors, water_colors)
+    }
+}
+
+/// Mesh of `hex_count` hexes at the origin, each vertex tagged with its hex's
+/// cell index
+pub fn create_instance_mesh(template: &HexTemplate, hex_count: usize) -> Mesh {
+    let vertex_count = template.vertices().len();
+    let mut positions = Vec::with_capacity(vertex_count * hex_count);
+    let mut cell_indices = Vec::with_capacity(vertex_count * hex_count);
+    let mut indices = Vec::with_capacity(template.indices().len() * hex_count);
+
+    for cell in 0..hex_count as u32 {
+        let first = positions.len() as u32;
+        positions.extend_from_slice(template.vertices());
+        cell_indices.extend(std::iter::repeat_n(cell, vertex_count));
+        indices.extend(template.indices().iter().map(|&index| index + first));
+    }
+
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(ATTRIBUTE_CELL_INDEX, cell_indices);
+    mesh.insert_indices(Indices::U32(indices));
+    mesh
+}
+
+/// Cell buffer contents of a chunk for the instanced renderer
+///
+/// The buffer is fitted to the shared hex mesh on upload. `shading` is
+/// computed even while hillshading is off, so toggling it only changes the
+/// material.
+pub fn create_instance_cells(
+    cells: &[Cell],
+    chunk_coords: (i32, i32),
+    layout: &HexLayout,
+    center_offset: Vec2,
+    shading: Option<(&Hillshade, &Heightfield)>,
+) -> (InstancedCells, ChunkMesh) {
+    let mut gpu_cells = Vec::with_capacity(cells.len());
+    let mut biomes = Vec::with_capacity(cells.len());
+    let mut min = Vec2::MAX;
+    let mut max = Vec2::MIN;
+
+    for cell in cells {
+        let center = layout.hex_to_world_pos(cell.hex()) - center_offset;
+        min = min.min(center);
+        max = max.max(center);
+
+        let water = cell.water();
+        let water_body = water.map_or(-1, |water| {
+            water.body.map_or(0, |body| {
+                WATER_BODIES
+                    .iter()
+                    .position(|&known| known == body)
+                    .map_or(0, |index| index as i32 + 1)
+            })
+        });
+
+        gpu_cells.push(GpuCell {
+            center,
+            biome: 0,
+            secondary_weight: cell.density().secondary_weight(),
+            elevation: (cell.elevation as f32 / 50.0).clamp(0.0, 1.0),
+            water_body,
+            water_depth: water.map_or(0.0, |water| water.normalized_depth()),
+            shade: shading.map_or(1.0, |(hillshade, heights)| {
+                hillshade.shade(heights, layout, cell)
+            }),
+            visible: 1,
+        });
+        biomes.push(cell.biome);
+    }
+
+    let hex_count = gpu_cells.len();
+
+    let instances = InstancedCells {
+        cells: gpu_cells,
+        biomes,
+    };
+    let chunk_mesh = ChunkMesh {
+        chunk_coords,
+        hex_count,
+        bounds: Rect::from_corners(min, max),
+    };
+
+    (instances, chunk_mesh)
+}
+
+/// System keeping instanced chunks in sync with the palette and overlays
+///
+/// Palette changes rewrite the shared color tables and overlay toggles
+/// change the materials or the contour layers' visibility, none of them
+/// re-meshes a chunk.
+#[allow(clippy::too_many_arguments)]
+pub fn update_hex_instancing(
+    mut instancing: ResMut<HexInstancing>,
+    world_config: Res<WorldConfig>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    active_palette: Res<ActivePalette>,
+    palettes: Res<Assets<BiomePalette>>,
+    mut palette_events: EventReader<AssetEvent<BiomePalette>>,
+    hillshade: Res<Hillshade>,
+    contours: Res<Contours>,
+    mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
+    mut materials: ResMut<Assets<HexInstanceMaterial>>,
+    mut contour_query: Query<&mut Visibility, With<ContourLayer>>,
+) {
+    // The chunk size is only known once the first chunks are loaded
+    let cells_per_chunk = world_config.grid.cells_per_chunk();
+    if cells_per_chunk != instancing.cells_per_chunk {
+        let template = HexTemplate::new(world_config.grid.layout());
+        instancing.mesh = meshes.add(create_instance_mesh(&template, cells_per_chunk));
+        instancing.cells_per_chunk = cells_per_chunk;
+    }
+
+    let mut palette_changed = active_palette.is_changed();
+    for event in palette_events.read() {
+        palette_changed |= event.is_modified(active_palette.handle())
+            || event.is_loaded_with_dependencies(active_palette.handle());
+    }
+
+    let rebuild = palette_changed || instancing.dirty;
+    if rebuild {
+        let Some(palette) = palettes.get(active_palette.handle()) else {
+            return;
+        };
+
+        let (biome_colors, water_colors) = instancing.color_tables(palette);
+        let biome_count = biome_colors.len();
+        buffers.insert(
+            &instancing.biome_colors,
+            ShaderStorageBuffer::from(biome_colors),
+        );
+        buffers.insert(
+            &instancing.water_colors,
+            ShaderStorageBuffer::from(water_colors),
+        );
+        instancing.dirty = false;
+
+        log::debug!(
+            target: RENDERING,
+            "Rebuilt hex color tables for {} biome values",
+            biome_count
+        );
+    }
+
+    // Touching every material also rebinds the rewritten color tables
+    if rebuild || hillshade.is_changed() {
+        for (_, material) in materials.iter_mut() {
+            material.params.hillshade = hillshade.enabled as u32;
+        }
+    }
+
+    if contours.is_changed() {
+        let visibility = if contours.enabled {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        for mut contour_visibility in contour_query.iter_mut() {
+            *contour_visibility = visibility;
+        }
+    }
+}
@@ -0,0 +1,466 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use spacetimedb_sdk::{DbContext, Table, TableWithPrimaryKey};
+use std::{
+    collections::HashSet,
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    net::TcpStream,
+    sync::{
+        Mutex,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+};
+
+use crate::{
+    log_targets::LOADING,
+    terrain::{
+        chunk::TerrainChunkState,
+        dynamic_chunks::{DynamicChunk, SpawnedChunks},
+        lod::group_of,
+        mesh_cache::MeshCache,
+        module_bindings::{DbConnection, TerrainChunkStateTableAccess},
+        validation::validate_dimension_side,
+        world_config::WorldConfig,
+        world_data::{DimensionData, WorldData},
+    },
+};
+
+/// Query subscribed to when reading the feed straight from SpacetimeDB
+const TERRAIN_QUERY: &str = "SELECT * FROM terrain_chunk_state";
+
+/// A single row event from the terrain chunk table
+///
+/// Events are exchanged as newline-delimited JSON, e.g.
+/// `{"op":"insert","row":{...}}`, so the same format works for recorded
+/// streams on disk and for a relay forwarding SpacetimeDB row callbacks.
+/// A direct SpacetimeDB subscription produces the same events.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum ChunkEvent {
+    Insert {
+        row: TerrainChunkState,
+    },
+    Update {
+        old: TerrainChunkState,
+        new: TerrainChunkState,
+    },
+    Delete {
+        row: TerrainChunkState,
+    },
+}
+
+/// Where live terrain events are read from
+#[derive(Clone, PartialEq, Debug)]
+pub enum FeedSource {
+    /// Replay a recorded event stream from a file
+    Recorded(String),
+    /// Connect to a relay or local stand-in streaming events over TCP
+    Tcp(String),
+    /// Subscribe to the terrain chunk table of a SpacetimeDB module
+    SpacetimeDb { uri: String, module: String },
+}
+
+impl FeedSource {
+    /// Parse a feed location
+    ///
+    /// `ws://host:port/<module>` and `wss://host/<module>` subscribe to a
+    /// SpacetimeDB module, `tcp://host:port` reads a relay and anything else
+    /// is the path to a recorded stream.
+    pub fn parse(location: &str) -> Self {
+        if (location.starts_with("ws://") || location.starts_with("wss://"))
+            && let Some((uri, module)) = location.rsplit_once('/')
+            && !module.is_empty()
+            && !uri.ends_with('/')
+        {
+            return FeedSource::SpacetimeDb {
+                uri: uri.to_string(),
+                module: module.to_string(),
+            };
+        }
+
+        match location.strip_prefix("tcp://") {
+            Some(addr) => FeedSource::Tcp(addr.to_string()),
+            None => FeedSource::Recorded(location.to_string()),
+        }
+    }
+}
+
+/// Why a terrain feed could not be started
+#[derive(Debug)]
+pub enum FeedError {
+    /// The recorded stream or relay could not be opened
+    Io { feed: FeedSource, error: io::Error },
+    /// The SpacetimeDB connection could not be established
+    SpacetimeDb {
+        feed: FeedSource,
+        error: spacetimedb_sdk::Error,
+    },
+}
+
+impl fmt::Display for FeedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeedError::Io { feed, error } => {
+                write!(f, "failed to open terrain feed {feed:?}: {error}")
+            }
+            FeedError::SpacetimeDb { feed, error } => {
+                write!(f, "failed to connect to terrain feed {feed:?}: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FeedError {}
+
+/// Resource receiving terrain row events from a background reader
+#[derive(Resource)]
+pub struct TerrainFeed {
+    receiver: Mutex<Receiver<ChunkEvent>>,
+}
+
+impl TerrainFeed {
+    /// Create a feed and the sender used to push events into it
+    pub fn channel() -> (Sender<ChunkEvent>, Self) {
+        let (sender, receiver) = mpsc::channel();
+        (
+            sender,
+            Self {
+                receiver: Mutex::new(receiver),
+            },
+        )
+    }
+
+    /// Start reading events from the given source on a background thread
+    pub fn connect(source: FeedSource) -> Result<Self, FeedError> {
+        let opened: io::Result<Box<dyn Read + Send>> = match &source {
+            FeedSource::Recorded(path) => File::open(path).map(|f| Box::new(f) as _),
+            FeedSource::Tcp(addr) => TcpStream::connect(addr).map(|s| Box::new(s) as _),
+            FeedSource::SpacetimeDb { uri, module } => {
+                let (sender, feed) = Self::channel();
+                return match subscribe_terrain(uri, module, sender) {
+                    Ok(()) => Ok(feed),
+                    Err(error) => Err(FeedError::SpacetimeDb {
+                        feed: source,
+                        error,
+                    }),
+                };
+            }
+        };
+        let reader = match opened {
+            Ok(reader) => reader,
+            Err(error) => {
+                return Err(FeedError::Io {
+                    feed: source,
+                    error,
+                });
+            }
+        };
+
+        let (sender, feed) = Self::channel();
+        thread::spawn(move || {
+            log::info!(target: LOADING, "Terrain feed connected: {source:?}");
+            read_events(BufReader::new(reader), &sender);
+            log::info!(target: LOADING, "Terrain feed closed: {source:?}");
+        });
+
+        Ok(feed)
+    }
+
+    /// Drain all events received since the last call
+    pub fn drain(&self) -> Vec<ChunkEvent> {
+        match self.receiver.lock() {
+            Ok(receiver) => receiver.try_iter().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+/// Connect to a SpacetimeDB module and forward row callbacks of the terrain
+/// chunk table to the feed
+///
+/// The initial subscription arrives as inserts, later commits as inserts,
+/// updates and deletes keyed by `chunk_index`.
+fn subscribe_terrain(
+    uri: &str,
+    module: &str,
+    sender: Sender<ChunkEvent>,
+) -> Result<(), spacetimedb_sdk::Error> {
+    let connection = DbConnection::builder()
+        .with_uri(uri)
+        .with_module_name(module)
+        .on_connect(|_, _, _| log::info!(target: LOADING, "Connected to SpacetimeDB"))
+        .on_disconnect(|_, error| match error {
+            Some(e) => log::warn!(target: LOADING, "SpacetimeDB connection lost: {e}"),
+            None => log::info!(target: LOADING, "SpacetimeDB connection closed"),
+        })
+        .build()?;
+
+    let terrain = connection.db.terrain_chunk_state();
+    let insert_sender = sender.clone();
+    terrain.on_insert(move |_, row| {
+        let _ = insert_sender.send(ChunkEvent::Insert { row: row.clone() });
+    });
+    let update_sender = sender.clone();
+    terrain.on_update(move |_, old, new| {
+        let _ = update_sender.send(ChunkEvent::Update {
+            old: old.clone(),
+            new: new.clone(),
+        });
+    });
+    terrain.on_delete(move |_, row| {
+        let _ = sender.send(ChunkEvent::Delete { row: row.clone() });
+    });
+
+    connection
+        .subscription_builder()
+        .on_applied(|_| log::info!(target: LOADING, "Terrain subscription applied"))
+        .on_error(|_, e| log::warn!(target: LOADING, "Terrain subscription failed: {e}"))
+        .subscribe(TERRAIN_QUERY);
+
+    // The connection lives on the thread processing its messages
+    thread::spawn(move || {
+        if connection.run_threaded().join().is_err() {
+            log::warn!(target: LOADING, "SpacetimeDB message thread panicked");
+        }
+    });
+
+    Ok(())
+}
+
+/// Forward every parseable line of an event stream to the feed
+pub fn read_events(reader: impl BufRead, sender: &Sender<ChunkEvent>) {
+    for line in reader.lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(e) => {
+                log::warn!(target: LOADING, "Error reading terrain feed: {e}");
+                return;
+            }
+        };
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match serde_json::from_str::<ChunkEvent>(&line) {
+            Ok(event) => {
+                if sender.send(event).is_err() {
+                    // Receiver dropped, the app is shutting down
+                    return;
+                }
+            }
+            Err(e) => log::warn!(target: LOADING, "Skipping malformed terrain event: {e}"),
+        }
+    }
+}
+
+/// Reject rows that would break meshing, the same way the file loader quarantines them
+fn event_is_valid(event: &ChunkEvent, world_data: &WorldData) -> bool {
+    let row = match event {
+        ChunkEvent::Insert { row } => row,
+        ChunkEvent::Update { new, .. } => new,
+        ChunkEvent::Delete { .. } => return true,
+    };
+
+    let mut violations = row.validate();
+    let expected = world_data
+        .dimensions
+        .get(&row.dimension)
+        .and_then(DimensionData::chunk_side);
+    violations.extend(validate_dimension_side(
+        row.dimension,
+        row.biomes.len(),
+        expected,
+    ));
+    if !violations.is_empty() {
+        log::warn!(
+            target: LOADING,
+            "Ignoring invalid terrain row at ({}, {}): {} violations",
+            row.chunk_x,
+            row.chunk_z,
+            violations.len()
+        );
+    }
+    violations.is_empty()
+}
+
+/// System applying received row events to the world and re-meshing affected chunks
+pub fn apply_terrain_feed(
+    mut commands: Commands,
+    feed: Res<TerrainFeed>,
+    mut world_data: ResMut<WorldData>,
+    mut world_config: ResMut<WorldConfig>,
+    mut spawned_chunks: ResMut<SpawnedChunks>,
+    mut mesh_cache: ResMut<MeshCache>,
+    chunk_query: Query<(Entity, &DynamicChunk)>,
+) {
+    let events = feed.drain();
+    if events.is_empty() {
+        return;
+    }
+
+    // Dimensions first seen in this batch need their centre computed once
+    let mut new_dimensions = HashSet::new();
+    let first_chunks = world_data.dimensions.is_empty();
+
+    let mut affected = HashSet::new();
+    for event in events {
+        if !event_is_valid(&event, &world_data) {
+            continue;
+        }
+        let rows = match &event {
+            ChunkEvent::Insert { row } | ChunkEvent::Delete { row } => vec![row],
+            ChunkEvent::Update { old, new } => vec![old, new],
+        };
+        for row in rows {
+            if !world_data.dimensions.contains_key(&row.dimension) {
+                new_dimensions.insert(row.dimension);
+            }
+            mesh_cache.invalidate(row.dimension, (row.chunk_x, row.chunk_z));
+            // Only the active dimension has spawned meshes
+            if row.dimension == world_data.active_dimension {
+                affected.insert(group_of((row.chunk_x, row.chunk_z), spawned_chunks.level));
+            }
+        }
+
+        match event {
+            ChunkEvent::Insert { row } => {
+                world_data.insert_chunk(row);
+            }
+            ChunkEvent::Update { old, new } => {
+                world_data.remove_chunk(&old);
+                world_data.insert_chunk(new);
+            }
+            ChunkEvent::Delete { row } => {
+                world_data.remove_chunk(&row);
+            }
+        }
+    }
+
+    // Without region files the first chunks received decide which dimension
+    // is the overworld and size the grid for it
+    if first_chunks {
+        world_data.classify();
+        if world_data.active().is_none()
+            && let Some(overworld) = world_data.classifier.overworld_dimension
+        {
+            world_data.active_dimension = overworld;
+        }
+        world_config.detect_grid(&world_data);
+    }
+
+    for id in new_dimensions {
+        if let Some(dimension) = world_data.dimensions.get_mut(&id) {
+            let grid = world_config.grid_for(dimension);
+            dimension.finalize(&grid);
+        }
+    }
+
+    // Drop stale meshes, update_dynamic_chunks respawns whatever is still in view
+    for (entity, dynamic_chunk) in chunk_query.iter() {
+        if affected.contains(&dynamic_chunk.chunk_coords) {
+            commands.entity(entity).despawn();
+            spawned_chunks.chunks.remove(&dynamic_chunk.chunk_coords);
+        }
+    }
+    spawned_chunks.needs_refresh = true;
+
+    log::debug!(target: LOADING, "Terrain feed: {} chunks changed", affected.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+    use std::io::Cursor;
+
+    /// Two inserts, a malformed line, an update, a delete and an insert into
+    /// a second dimension, as recorded from a relay
+    const FIXTURE: &str = include_str!("../../tests/fixtures/terrain_feed.ndjson");
+
+    fn world_with_feed(feed: TerrainFeed) -> World {
+        let mut world = World::new();
+        world.insert_resource(feed);
+        world.insert_resource(WorldData::new());
+        world.insert_resource(WorldConfig::default());
+        world.insert_resource(SpawnedChunks::default());
+        world.insert_resource(MeshCache::new(0));
+        world
+    }
+
+    fn elevation_at(world: &World, dimension: u32, coords: (i32, i32)) -> Option<i16> {
+        let chunk = world
+            .resource::<WorldData>()
+            .dimensions
+            .get(&dimension)?
+            .get_chunk(coords.0, coords.1)?;
+        Some(chunk.elevations[0])
+    }
+
+    #[test]
+    fn read_events_skips_malformed_lines() {
+        let (sender, feed) = TerrainFeed::channel();
+        read_events(Cursor::new(FIXTURE), &sender);
+
+        let events = feed.drain();
+        assert_eq!(events.len(), 5);
+        assert!(matches!(events[0], ChunkEvent::Insert { .. }));
+        assert!(matches!(events[2], ChunkEvent::Update { .. }));
+        assert!(matches!(events[3], ChunkEvent::Delete { .. }));
+    }
+
+    #[test]
+    fn apply_terrain_feed_inserts_updates_and_deletes() {
+        let (sender, feed) = TerrainFeed::channel();
+        let mut world = world_with_feed(feed);
+
+        // Inserts, the malformed third line is dropped
+        let inserts: Vec<_> = FIXTURE.lines().take(3).collect();
+        read_events(Cursor::new(inserts.join("\n")), &sender);
+        world.run_system_once(apply_terrain_feed).unwrap();
+        assert_eq!(elevation_at(&world, 1, (0, 0)), Some(10));
+        assert_eq!(elevation_at(&world, 1, (1, 0)), Some(20));
+        assert_eq!(world.resource::<WorldData>().chunk_side(), Some(2));
+
+        // Update of the first chunk and delete of the second
+        let changes: Vec<_> = FIXTURE.lines().skip(3).take(2).collect();
+        read_events(Cursor::new(changes.join("\n")), &sender);
+        world.run_system_once(apply_terrain_feed).unwrap();
+        assert_eq!(elevation_at(&world, 1, (0, 0)), Some(15));
+        assert_eq!(elevation_at(&world, 1, (1, 0)), None);
+
+        // Insert into a dimension not seen before
+        let new_dimension: Vec<_> = FIXTURE.lines().skip(5).collect();
+        read_events(Cursor::new(new_dimension.join("\n")), &sender);
+        world.run_system_once(apply_terrain_feed).unwrap();
+        let world_data = world.resource::<WorldData>();
+        assert_eq!(
+            world_data.dimensions.keys().copied().collect::<Vec<_>>(),
+            [1, 2]
+        );
+        assert_eq!(elevation_at(&world, 2, (0, 0)), Some(30));
+        assert!(world.resource::<SpawnedChunks>().needs_refresh);
+    }
+
+    #[test]
+    fn parse_feed_locations() {
+        assert_eq!(
+            FeedSource::parse("ws://localhost:3000/bitcraft"),
+            FeedSource::SpacetimeDb {
+                uri: "ws://localhost:3000".to_string(),
+                module: "bitcraft".to_string(),
+            }
+        );
+        assert_eq!(
+            FeedSource::parse("tcp://localhost:7000"),
+            FeedSource::Tcp("localhost:7000".to_string())
+        );
+        assert_eq!(
+            FeedSource::parse("recordings/terrain.ndjson"),
+            FeedSource::Recorded("recordings/terrain.ndjson".to_string())
+        );
+    }
+}
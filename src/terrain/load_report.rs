@@ -0,0 +1,144 @@
+use spacetimedb_lib::buffer::DecodeError;
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{log_targets::LOADING, terrain::validation::QuarantinedChunk};
+
+/// Errors that can occur while loading terrain chunk dumps
+///
+/// Paths are kept as `PathBuf` from listing to opening, so files with names
+/// that are not valid UTF-8 load like any other and are only displayed lossily.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The file or directory could not be read
+    Io { path: PathBuf, source: io::Error },
+    /// The file is not valid BSATN for a list of terrain chunks
+    Decode { path: PathBuf, source: DecodeError },
+    /// The file decoded but left bytes unread, so it was written with a different schema
+    SchemaMismatch {
+        path: PathBuf,
+        trailing_bytes: usize,
+    },
+    /// The file ends or breaks in the middle of a chunk record
+    Corrupt { path: PathBuf, offset: usize },
+}
+
+impl LoadError {
+    /// The file or directory the error refers to
+    pub fn path(&self) -> &Path {
+        match self {
+            LoadError::Io { path, .. }
+            | LoadError::Decode { path, .. }
+            | LoadError::SchemaMismatch { path, .. }
+            | LoadError::Corrupt { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io { path, source } => {
+                write!(f, "failed to read {}: {source}", path.display())
+            }
+            LoadError::Decode { path, source } => {
+                write!(f, "failed to decode {}: {source}", path.display())
+            }
+            LoadError::SchemaMismatch {
+                path,
+                trailing_bytes,
+            } => write!(
+                f,
+                "schema mismatch in {}: {trailing_bytes} trailing bytes after decoding",
+                path.display()
+            ),
+            LoadError::Corrupt { path, offset } => {
+                write!(
+                    f,
+                    "corrupt chunk data in {} at byte {offset}",
+                    path.display()
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Io { source, .. } => Some(source),
+            LoadError::Decode { source, .. } => Some(source),
+            LoadError::SchemaMismatch { .. } | LoadError::Corrupt { .. } => None,
+        }
+    }
+}
+
+/// Outcome of loading a single region file
+#[derive(Debug)]
+pub struct FileReport {
+    pub path: PathBuf,
+    /// Number of valid chunks the file yielded
+    pub chunks: usize,
+    /// Chunks that decoded but failed validation
+    pub quarantined: Vec<QuarantinedChunk>,
+    /// Why the file was rejected, if it was
+    pub error: Option<LoadError>,
+}
+
+/// Per-file summary of a directory load
+#[derive(Debug, Default)]
+pub struct LoadReport {
+    pub files: Vec<FileReport>,
+}
+
+impl LoadReport {
+    /// Total number of chunks loaded across all files
+    pub fn total_chunks(&self) -> usize {
+        self.files.iter().map(|file| file.chunks).sum()
+    }
+
+    /// Total number of chunks rejected by validation across all files
+    pub fn total_quarantined(&self) -> usize {
+        self.files.iter().map(|file| file.quarantined.len()).sum()
+    }
+
+    /// Files that could not be loaded
+    pub fn failures(&self) -> impl Iterator<Item = &FileReport> {
+        self.files.iter().filter(|file| file.error.is_some())
+    }
+
+    /// Whether every file and every chunk was loaded without errors
+    pub fn is_complete(&self) -> bool {
+        self.failures().next().is_none() && self.total_quarantined() == 0
+    }
+
+    /// Log one line per file, failures as errors
+    pub fn log(&self) {
+        for file in &self.files {
+            match &file.error {
+                Some(e) => log::error!(target: LOADING, "{}: rejected ({e})", file.path.display()),
+                None if !file.quarantined.is_empty() => log::warn!(
+                    target: LOADING,
+                    "{}: {} chunks, {} quarantined",
+                    file.path.display(),
+                    file.chunks,
+                    file.quarantined.len()
+                ),
+                None => {
+                    log::info!(target: LOADING, "{}: {} chunks", file.path.display(), file.chunks)
+                }
+            }
+        }
+
+        log::info!(
+            target: LOADING,
+            "Loaded {} chunks from {} files ({} failed, {} chunks quarantined)",
+            self.total_chunks(),
+            self.files.len(),
+            self.failures().count(),
+            self.total_quarantined()
+        );
+    }
+}
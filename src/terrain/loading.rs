@@ -0,0 +1,340 @@
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task, block_on, poll_once},
+};
+use std::{collections::HashSet, sync::Arc};
+
+use crate::{
+    cli::ViewerOptions,
+    log_targets::LOADING,
+    terrain::{
+        dynamic_chunks::SpawnedChunks,
+        live_feed::TerrainFeed,
+        load_report::{FileReport, LoadReport},
+        region_file::RegionFile,
+        world_config::WorldConfig,
+        world_data::WorldData,
+    },
+};
+
+/// Top-level phases of the viewer
+#[derive(States, Default, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum AppState {
+    /// Region files are being indexed in the background
+    #[default]
+    Loading,
+    /// The map is shown and chunks stream in with the camera, remaining
+    /// region files are added as they are indexed
+    Viewing,
+}
+
+/// Counters shown on the loading screen
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LoadProgress {
+    pub files_total: usize,
+    pub files_done: usize,
+    pub chunks_loaded: usize,
+    /// Size of the mapped region files, their chunks are only decoded in view
+    pub bytes_mapped: usize,
+}
+
+/// Resource tracking region files being indexed on the task pool
+#[derive(Resource, Default)]
+pub struct RegionLoading {
+    tasks: Vec<Task<(FileReport, Option<RegionFile>)>>,
+    pub progress: LoadProgress,
+    /// Per-file outcome, complete once loading has finished
+    pub report: LoadReport,
+    /// Dimensions whose centre was computed when viewing started, kept so
+    /// the camera does not jump as more regions arrive
+    pub shown_dimensions: Option<HashSet<u32>>,
+}
+
+/// Marker for the loading screen text
+#[derive(Component)]
+pub struct LoadingScreen;
+
+/// Start indexing every region file in parallel
+///
+/// Indexing only reads chunk headers, chunks are decoded once they come into
+/// view. The map is shown once every index is built, or earlier with
+/// `--start` as soon as the start chunk has been indexed.
+pub fn start_region_loading(
+    mut commands: Commands,
+    options: Res<ViewerOptions>,
+    feed: Option<Res<TerrainFeed>>,
+    mut world_data: ResMut<WorldData>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    // A live feed delivers its chunks while viewing
+    if feed.is_some() {
+        if let Some(dimension) = options.dimension {
+            world_data.active_dimension = dimension;
+        }
+        next_state.set(AppState::Viewing);
+        return;
+    }
+
+    // Unreadable directories show up in the report like unreadable files
+    let mut report = LoadReport::default();
+    let mut paths = Vec::new();
+    for data_path in &options.data_paths {
+        if !data_path.is_dir() {
+            paths.push(data_path.clone());
+            continue;
+        }
+
+        match RegionFile::list_dir(data_path) {
+            Ok(found) => paths.extend(found),
+            Err(e) => {
+                log::error!(target: LOADING, "Could not read data dir: {e}");
+                report.files.push(FileReport {
+                    path: data_path.clone(),
+                    chunks: 0,
+                    quarantined: Vec::new(),
+                    error: Some(e),
+                });
+            }
+        }
+    }
+    log::info!(target: LOADING, "Indexing {} region files...", paths.len());
+
+    let pool = AsyncComputeTaskPool::get();
+    let files_total = paths.len();
+    let tasks = paths
+        .into_iter()
+        .map(|path| pool.spawn(async move { RegionFile::load(path) }))
+        .collect();
+
+    commands.insert_resource(RegionLoading {
+        tasks,
+        progress: LoadProgress {
+            files_total,
+            ..default()
+        },
+        report,
+        shown_dimensions: None,
+    });
+
+    commands.spawn((
+        LoadingScreen,
+        Text::new("Loading..."),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(12.0),
+            left: Val::Px(12.0),
+            ..default()
+        },
+    ));
+}
+
+/// System collecting finished region files into the world
+///
+/// Runs in both states: viewing starts once the start chunk is in and the
+/// remaining regions keep streaming into the world. Strict mode waits for
+/// every file, so an incomplete load exits before anything is shown.
+#[allow(clippy::too_many_arguments)]
+pub fn poll_region_loading(
+    mut commands: Commands,
+    options: Res<ViewerOptions>,
+    mut loading: ResMut<RegionLoading>,
+    mut world_data: ResMut<WorldData>,
+    mut world_config: ResMut<WorldConfig>,
+    mut spawned_chunks: ResMut<SpawnedChunks>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut exit: EventWriter<AppExit>,
+    screen_query: Query<Entity, With<LoadingScreen>>,
+) {
+    let mut finished = Vec::new();
+    loading
+        .tasks
+        .retain_mut(|task| match block_on(poll_once(task)) {
+            Some(result) => {
+                finished.push(result);
+                false
+            }
+            None => true,
+        });
+
+    let regions_added = !finished.is_empty();
+    for (mut file_report, region) in finished {
+        loading.progress.files_done += 1;
+
+        if let Some(region) = region {
+            loading.progress.bytes_mapped += region.byte_len();
+            let quarantined = world_data.add_region_file(Arc::new(region));
+            file_report.chunks -= quarantined.len();
+            file_report.quarantined.extend(quarantined);
+        }
+        loading.progress.chunks_loaded += file_report.chunks;
+        loading.report.files.push(file_report);
+    }
+
+    let done = loading.tasks.is_empty();
+    if *state.get() == AppState::Viewing && regions_added {
+        // New chunks may lie in the current view
+        spawned_chunks.needs_refresh = true;
+    }
+
+    if done {
+        loading.report.log();
+        if options.strict && !loading.report.is_complete() {
+            log::error!(
+                target: LOADING,
+                "Strict mode: {} files failed and {} chunks were quarantined",
+                loading.report.failures().count(),
+                loading.report.total_quarantined()
+            );
+            exit.write(AppExit::from_code(1));
+            return;
+        }
+    }
+
+    let shown_early = loading.shown_dimensions.is_some();
+    if *state.get() == AppState::Loading {
+        let start_ready =
+            !options.strict && regions_added && start_chunk_indexed(&options, &mut world_data);
+        if !done && !start_ready {
+            return;
+        }
+
+        show_world_data(&mut world_data, &mut world_config, options.dimension);
+        loading.shown_dimensions = Some(world_data.dimensions.keys().copied().collect());
+        next_state.set(AppState::Viewing);
+        if !done {
+            log::info!(
+                target: LOADING,
+                "Start chunk loaded, showing the map while {} files are indexed",
+                loading.tasks.len()
+            );
+            return;
+        }
+    }
+
+    if !done {
+        return;
+    }
+
+    if shown_early {
+        settle_world_data(
+            &mut world_data,
+            &world_config,
+            loading.shown_dimensions.as_ref(),
+        );
+    }
+    log_dimensions(&world_data);
+    spawned_chunks.needs_refresh = true;
+
+    commands.remove_resource::<RegionLoading>();
+    for entity in screen_query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Whether the chunk given with `--start` is on the grid of the dimension to show
+///
+/// Without `--dimension` the overworld is guessed from the regions loaded so
+/// far, the final classification runs once every region is in.
+fn start_chunk_indexed(options: &ViewerOptions, world_data: &mut WorldData) -> bool {
+    let Some(start_chunk) = options.start_chunk else {
+        return false;
+    };
+
+    let dimension = match options.dimension {
+        Some(dimension) => dimension,
+        None => {
+            world_data.classify();
+            match world_data.classifier.overworld_dimension {
+                Some(dimension) => dimension,
+                None => return false,
+            }
+        }
+    };
+
+    world_data
+        .dimensions
+        .get(&dimension)
+        .is_some_and(|data| data.chunks.contains_key(&start_chunk))
+}
+
+/// Pick the overworld, size the grid and compute dimension centres of the
+/// regions loaded so far, right before the map is shown
+fn show_world_data(
+    world_data: &mut WorldData,
+    world_config: &mut WorldConfig,
+    start_dimension: Option<u32>,
+) {
+    world_data.classify();
+    let overworld = world_data.classifier.overworld_dimension;
+    world_data.active_dimension = start_dimension.or(overworld).unwrap_or_default();
+    log::info!(
+        target: LOADING,
+        "Using dimension {overworld:?} as overworld, showing dimension {}",
+        world_data.active_dimension
+    );
+
+    world_config.detect_grid(world_data);
+    world_data.finalize(world_config);
+}
+
+/// Classify against the whole dump once every region is in, a single region
+/// may hold no surface chunks
+///
+/// Dimensions already on screen keep their centre so the camera stays put,
+/// dimensions first seen since get theirs now.
+fn settle_world_data(
+    world_data: &mut WorldData,
+    world_config: &WorldConfig,
+    shown_dimensions: Option<&HashSet<u32>>,
+) {
+    world_data.classify();
+
+    for (id, dimension) in world_data.dimensions.iter_mut() {
+        if shown_dimensions.is_none_or(|shown| !shown.contains(id)) {
+            let grid = world_config.grid_for(dimension);
+            dimension.finalize(&grid);
+        }
+    }
+}
+
+fn log_dimensions(world_data: &WorldData) {
+    for (id, dimension) in &world_data.dimensions {
+        let (min_x, max_x, min_z, max_z) = dimension.bounds;
+        log::info!(
+            target: LOADING,
+            "Dimension {} ({:?}): {} chunks, {} interiors, X=[{}, {}], Z=[{}, {}], center offset: ({:.1}, {:.1})",
+            id,
+            world_data.classifier.classify_dimension(*id),
+            dimension.chunks.len(),
+            dimension.interiors.len(),
+            min_x,
+            max_x,
+            min_z,
+            max_z,
+            dimension.center_offset.x,
+            dimension.center_offset.y
+        );
+    }
+}
+
+/// System showing loading progress on screen
+pub fn update_loading_screen(
+    loading: Res<RegionLoading>,
+    mut text_query: Query<&mut Text, With<LoadingScreen>>,
+) {
+    if !loading.is_changed() {
+        return;
+    }
+
+    let progress = loading.progress;
+    for mut text in text_query.iter_mut() {
+        text.0 = format!(
+            "Loading regions: {}/{} files, {} chunks, {:.1} MiB mapped",
+            progress.files_done,
+            progress.files_total,
+            progress.chunks_loaded,
+            progress.bytes_mapped as f64 / (1024.0 * 1024.0)
+        );
+    }
+}
@@ -0,0 +1,369 @@
+use crate::terrain::{
+    cell::Cell,
+    chunk::TerrainChunkState,
+    world_data::{ChunkSlot, DimensionData},
+};
+
+/// Zoom at which each coarser level of detail takes over
+///
+/// Past the first threshold 2x2 chunks are drawn as one, then 4x4 and 8x8.
+pub const LOD_ZOOM_THRESHOLDS: [f32; 3] = [2.0, 4.0, 7.0];
+
+/// Level of detail for an orthographic zoom, 0 is full resolution
+pub fn lod_level(zoom: f32) -> u8 {
+    LOD_ZOOM_THRESHOLDS
+        .iter()
+        .filter(|&&threshold| zoom >= threshold)
+        .count() as u8
+}
+
+/// Chunks along each side of a superchunk
+pub fn group_size(level: u8) -> i32 {
+    1 << level
+}
+
+/// Coordinates of the superchunk holding a chunk
+pub fn group_of((chunk_x, chunk_z): (i32, i32), level: u8) -> (i32, i32) {
+    let size = group_size(level);
+    (chunk_x.div_euclid(size), chunk_z.div_euclid(size))
+}
+
+/// Whether any chunk of a superchunk is loaded
+pub fn has_chunks(dimension: &DimensionData, level: u8, (group_x, group_z): (i32, i32)) -> bool {
+    let size = group_size(level);
+    (0..size).any(|dx| {
+        (0..size).any(|dz| {
+            dimension
+                .chunks
+                .contains_key(&(group_x * size + dx, group_z * size + dz))
+        })
+    })
+}
+
+/// A square group of chunks down-sampled to the cell count of one chunk
+///
+/// `chunk` is laid out like a chunk at the superchunk's coordinates, so it is
+/// meshed like any chunk and drawn `scale` times larger. Each of its cells has
+/// the dominant biome and the average elevation of the cells it covers.
+pub struct Superchunk {
+    pub level: u8,
+    pub chunk: TerrainChunkState,
+    /// Whether any loaded cell falls into each down-sampled cell
+    covered: Vec<bool>,
+}
+
+/// Running totals for one down-sampled cell
+#[derive(Default)]
+struct CellTotals {
+    count: i32,
+    elevation: i32,
+    water_level: i32,
+    original_elevation: i32,
+    /// Cells per packed biome, with the first such cell as representative
+    biomes: Vec<(u32, u32, usize)>,
+}
+
+impl Superchunk {
+    /// Loaded chunks of a superchunk with their offsets in it
+    ///
+    /// Only the slots are collected, the chunks are decoded and down-sampled
+    /// off the main thread.
+    pub fn members(
+        dimension: &DimensionData,
+        level: u8,
+        (group_x, group_z): (i32, i32),
+    ) -> Vec<((i32, i32), ChunkSlot)> {
+        let size = group_size(level);
+        (0..size)
+            .flat_map(|dx| (0..size).map(move |dz| (dx, dz)))
+            .filter_map(|(dx, dz)| {
+                let slot = dimension
+                    .chunks
+                    .get(&(group_x * size + dx, group_z * size + dz))?;
+                Some(((dx, dz), slot.clone()))
+            })
+            .collect()
+    }
+
+    /// Decode the members collected by `members`, skipping chunks that fail
+    pub fn decode_members(
+        members: &[((i32, i32), ChunkSlot)],
+    ) -> Vec<((i32, i32), TerrainChunkState)> {
+        members
+            .iter()
+            .filter_map(|(offset, slot)| Some((*offset, slot.load()?.into_owned())))
+            .collect()
+    }
+
+    /// Down-sample the `members` of a superchunk, `None` when there are none
+    pub fn build(
+        level: u8,
+        (group_x, group_z): (i32, i32),
+        members: &[((i32, i32), TerrainChunkState)],
+    ) -> Option<Self> {
+        let size = group_size(level);
+        let first = &members.first()?.1;
+        let side_len = first.side_len()?;
+        let cell_count = side_len * side_len;
+        let mut totals: Vec<CellTotals> = (0..cell_count).map(|_| CellTotals::default()).collect();
+
+        for (member, ((dx, dz), chunk)) in members.iter().enumerate() {
+            // Chunks of another size cannot be placed on the grid
+            if chunk.side_len() != Some(side_len) {
+                continue;
+            }
+
+            for i in 0..side_len {
+                for j in 0..side_len {
+                    let source = i * side_len + j;
+                    let x = (*dx as usize * side_len + i) / size as usize;
+                    let z = (*dz as usize * side_len + j) / size as usize;
+                    let cell = &mut totals[x * side_len + z];
+
+                    cell.count += 1;
+                    cell.elevation += chunk.elevations[source] as i32;
+                    cell.water_level += chunk.water_levels[source] as i32;
+                    cell.original_elevation += chunk.original_elevations[source] as i32;
+
+                    let biome = chunk.biomes[source];
+                    match cell.biomes.iter_mut().find(|(b, _, _)| *b == biome) {
+                        Some((_, n, _)) => *n += 1,
+                        None => cell.biomes.push((biome, 1, member * cell_count + source)),
+                    }
+                }
+            }
+        }
+
+        let mut chunk = TerrainChunkState {
+            chunk_index: first.chunk_index,
+            chunk_x: group_x,
+            chunk_z: group_z,
+            dimension: first.dimension,
+            biomes: Vec::with_capacity(cell_count),
+            biome_density: Vec::with_capacity(cell_count),
+            elevations: Vec::with_capacity(cell_count),
+            water_levels: Vec::with_capacity(cell_count),
+            water_body_types: Vec::with_capacity(cell_count),
+            zoning_types: Vec::with_capacity(cell_count),
+            original_elevations: Vec::with_capacity(cell_count),
+        };
+        let mut covered = Vec::with_capacity(cell_count);
+
+        for cell in &totals {
+            let count = cell.count.max(1);
+            // Ties go to the biome seen first
+            let (biome, representative) = cell
+                .biomes
+                .iter()
+                .rev()
+                .max_by_key(|(_, n, _)| *n)
+                .map_or((0, None), |&(biome, _, at)| (biome, Some(at)));
+            let from_representative = |field: fn(&TerrainChunkState, usize) -> u32| {
+                representative.map_or(0, |at| field(&members[at / cell_count].1, at % cell_count))
+            };
+
+            covered.push(cell.count > 0);
+            chunk.biomes.push(biome);
+            chunk
+                .biome_density
+                .push(from_representative(|c, i| c.biome_density[i]));
+            chunk.elevations.push((cell.elevation / count) as i16);
+            chunk.water_levels.push((cell.water_level / count) as i16);
+            chunk
+                .water_body_types
+                .push(from_representative(|c, i| c.water_body_types[i] as u32) as u8);
+            chunk
+                .zoning_types
+                .push(from_representative(|c, i| c.zoning_types[i] as u32) as u8);
+            chunk
+                .original_elevations
+                .push((cell.original_elevation / count) as i16);
+        }
+
+        Some(Self {
+            level,
+            chunk,
+            covered,
+        })
+    }
+
+    /// How much larger than a chunk the superchunk is drawn
+    pub fn scale(&self) -> f32 {
+        group_size(self.level) as f32
+    }
+
+    /// Down-sampled cells that cover loaded terrain
+    pub fn cells(&self) -> Vec<Cell> {
+        self.chunk
+            .cells()
+            .into_iter()
+            .zip(&self.covered)
+            .filter_map(|(cell, &covered)| covered.then_some(cell))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn chunk(chunk_x: i32, chunk_z: i32) -> TerrainChunkState {
+        sized_chunk(chunk_x, chunk_z, 2)
+    }
+
+    fn sized_chunk(chunk_x: i32, chunk_z: i32, side: usize) -> TerrainChunkState {
+        let cells = side * side;
+        TerrainChunkState {
+            chunk_index: 0,
+            chunk_x,
+            chunk_z,
+            dimension: 1,
+            biomes: vec![1; cells],
+            biome_density: vec![0; cells],
+            elevations: vec![0; cells],
+            water_levels: vec![0; cells],
+            water_body_types: vec![0; cells],
+            zoning_types: vec![0; cells],
+            original_elevations: vec![0; cells],
+        }
+    }
+
+    /// Dimension with every chunk from -8 to 7 along both axes loaded
+    fn dimension() -> DimensionData {
+        let mut dimension = DimensionData::default();
+        for chunk_x in -8..8 {
+            for chunk_z in -8..8 {
+                let slot = ChunkSlot::Decoded(Arc::new(chunk(chunk_x, chunk_z)));
+                dimension.chunks.insert((chunk_x, chunk_z), slot);
+            }
+        }
+        dimension
+    }
+
+    #[test]
+    fn level_follows_zoom_thresholds() {
+        assert_eq!(lod_level(0.1), 0);
+        assert_eq!(lod_level(1.99), 0);
+        assert_eq!(lod_level(2.0), 1);
+        assert_eq!(lod_level(3.9), 1);
+        assert_eq!(lod_level(4.0), 2);
+        assert_eq!(lod_level(7.0), 3);
+        assert_eq!(lod_level(10.0), 3);
+    }
+
+    #[test]
+    fn groups_round_towards_negative_infinity() {
+        assert_eq!(group_of((5, -5), 0), (5, -5));
+        assert_eq!(group_of((-1, 1), 1), (-1, 0));
+        assert_eq!(group_of((-2, -3), 1), (-1, -2));
+        assert_eq!(group_of((-4, 3), 2), (-1, 0));
+        assert_eq!(group_of((-5, 4), 2), (-2, 1));
+        assert_eq!(group_of((-1, -8), 3), (-1, -1));
+        assert_eq!(group_of((-9, 7), 3), (-2, 0));
+    }
+
+    #[test]
+    fn members_cover_their_group_at_every_level() {
+        let dimension = dimension();
+        for level in 0..=3 {
+            let size = group_size(level);
+            for group in [(0, 0), (-1, -1), (-1, 0)] {
+                let members = Superchunk::members(&dimension, level, group);
+                assert_eq!(members.len(), (size * size) as usize);
+
+                for ((dx, dz), slot) in &members {
+                    assert!((0..size).contains(dx) && (0..size).contains(dz));
+                    let coords = (group.0 * size + dx, group.1 * size + dz);
+                    assert_eq!(slot.key().coords(), coords);
+                    assert_eq!(group_of(coords, level), group);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn members_skip_unloaded_chunks() {
+        let mut dimension = dimension();
+        dimension.chunks.remove(&(-7, 6));
+
+        let members = Superchunk::members(&dimension, 1, (-4, 3));
+        let offsets: Vec<_> = members.iter().map(|(offset, _)| *offset).collect();
+        assert_eq!(offsets, [(0, 0), (0, 1), (1, 1)]);
+
+        // Chunks -16 to -9 along x are not loaded
+        assert!(Superchunk::members(&dimension, 3, (-2, 0)).is_empty());
+        assert!(!has_chunks(&dimension, 3, (-2, 0)));
+    }
+
+    // With 2x2 cell chunks at level 1, each down-sampled cell covers exactly
+    // one member chunk: cell (dx, dz) of the superchunk is member (dx, dz).
+
+    #[test]
+    fn build_averages_the_cells_it_covers() {
+        let mut first = chunk(-2, 4);
+        first.elevations = vec![10, 20, 30, 41];
+        first.water_levels = vec![1, 2, 3, 6];
+        first.original_elevations = vec![100, 100, 200, 200];
+        let mut last = chunk(-1, 5);
+        last.elevations = vec![-4, -4, -8, -8];
+
+        let superchunk = Superchunk::build(1, (-1, 2), &[((0, 0), first), ((1, 1), last)]).unwrap();
+        let chunk = &superchunk.chunk;
+        assert_eq!((chunk.chunk_x, chunk.chunk_z), (-1, 2));
+        assert_eq!(superchunk.scale(), 2.0);
+
+        assert_eq!(chunk.elevations[0], 25);
+        assert_eq!(chunk.water_levels[0], 3);
+        assert_eq!(chunk.original_elevations[0], 150);
+        assert_eq!(chunk.elevations[3], -6);
+    }
+
+    #[test]
+    fn build_keeps_the_dominant_biome() {
+        let mut tied = chunk(0, 0);
+        tied.biomes = vec![5, 8, 8, 5];
+        tied.biome_density = vec![0x11, 0x22, 0x33, 0x44];
+        let mut clear = chunk(0, 1);
+        clear.biomes = vec![3, 7, 7, 7];
+        clear.biome_density = vec![1, 2, 3, 4];
+        clear.water_body_types = vec![0, 9, 0, 0];
+
+        let superchunk = Superchunk::build(1, (0, 0), &[((0, 0), tied), ((0, 1), clear)]).unwrap();
+        let chunk = &superchunk.chunk;
+
+        // A tie goes to the biome seen first, with that cell's other fields
+        assert_eq!(chunk.biomes[0], 5);
+        assert_eq!(chunk.biome_density[0], 0x11);
+        assert_eq!(chunk.biomes[1], 7);
+        assert_eq!(chunk.biome_density[1], 2);
+        assert_eq!(chunk.water_body_types[1], 9);
+    }
+
+    #[test]
+    fn build_covers_only_loaded_members() {
+        assert!(Superchunk::build(1, (0, 0), &[]).is_none());
+
+        let mut member = chunk(1, 0);
+        member.elevations = vec![8; 4];
+        let superchunk = Superchunk::build(1, (0, 0), &[((1, 0), member)]).unwrap();
+
+        assert_eq!(superchunk.covered, [false, false, true, false]);
+        let cells = superchunk.cells();
+        assert_eq!(cells.len(), 1);
+        assert_eq!(cells[0].elevation, 8);
+        assert_eq!(superchunk.chunk.elevations[0], 0);
+    }
+
+    #[test]
+    fn build_skips_members_of_another_side() {
+        let mut wide = sized_chunk(1, 1, 3);
+        wide.elevations = vec![50; 9];
+        let superchunk =
+            Superchunk::build(1, (0, 0), &[((0, 0), chunk(0, 0)), ((1, 1), wide)]).unwrap();
+
+        assert_eq!(superchunk.chunk.side_len(), Some(2));
+        assert_eq!(superchunk.covered, [true, false, false, false]);
+        assert_eq!(superchunk.chunk.elevations[3], 0);
+    }
+}
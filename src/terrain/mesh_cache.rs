@@ -0,0 +1,319 @@
+use bevy::{prelude::*, render::mesh::Indices};
+use std::collections::HashMap;
+
+use crate::{
+    cli::ViewerOptions,
+    log_targets::RENDERING,
+    terrain::{
+        chunk_mesh::ChunkMesh, instanced::HexInstanceMaterial, lod::group_of, view_mode::ViewMode,
+    },
+};
+
+/// What a chunk mesh was built from, meshes are reused only for equal keys
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct MeshKey {
+    pub dimension: u32,
+    /// Chunk coordinates, or superchunk coordinates above level 0
+    pub chunk_coords: (i32, i32),
+    pub level: u8,
+    pub view_mode: ViewMode,
+    /// `ActivePalette::version` the colors came from, `None` for instanced
+    /// chunks, which are colored on the GPU
+    pub palette_version: Option<(usize, u32)>,
+    /// Whether hillshading and contour lines were drawn
+    pub overlays: (bool, bool),
+}
+
+/// Meshes of a chunk entity that can be spawned again without meshing
+#[derive(Clone)]
+pub struct CachedMesh {
+    pub mesh: Handle<Mesh>,
+    /// Material of an instanced chunk, holding its cell buffer
+    pub material: Option<Handle<HexInstanceMaterial>>,
+    pub contours: Option<Handle<Mesh>>,
+    pub chunk_mesh: ChunkMesh,
+}
+
+struct CacheEntry {
+    cached: CachedMesh,
+    bytes: usize,
+    last_used: u64,
+}
+
+/// Chunk meshes kept after their entities despawn, least recently used
+/// meshes are dropped first once the budget is exceeded
+///
+/// The budget covers the vertex and index data of the cached meshes, which
+/// includes those of chunks still on screen.
+#[derive(Resource)]
+pub struct MeshCache {
+    entries: HashMap<MeshKey, CacheEntry>,
+    budget_bytes: usize,
+    used_bytes: usize,
+    /// Incremented on every use, orders entries by recency
+    clock: u64,
+}
+
+impl MeshCache {
+    pub fn new(budget_bytes: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            budget_bytes,
+            used_bytes: 0,
+            clock: 0,
+        }
+    }
+
+    /// Cache sized by `--mesh-cache`
+    pub fn from_options(options: &ViewerOptions) -> Self {
+        Self::new(options.mesh_cache_mb * 1024 * 1024)
+    }
+
+    /// Cached meshes for a key, marking them as recently used
+    pub fn get(&mut self, key: &MeshKey) -> Option<CachedMesh> {
+        self.clock += 1;
+        let entry = self.entries.get_mut(key)?;
+        entry.last_used = self.clock;
+        Some(entry.cached.clone())
+    }
+
+    /// Keep meshes for a key, `bytes` being their vertex and index data
+    pub fn insert(&mut self, key: MeshKey, cached: CachedMesh, bytes: usize) {
+        self.clock += 1;
+        let entry = CacheEntry {
+            cached,
+            bytes,
+            last_used: self.clock,
+        };
+        if let Some(replaced) = self.entries.insert(key, entry) {
+            self.used_bytes -= replaced.bytes;
+        }
+        self.used_bytes += bytes;
+        self.evict();
+    }
+
+    /// Drop meshes of every level that show a chunk or border on it
+    ///
+    /// Neighbours are included because shading and contour lines read the
+    /// heights across chunk borders.
+    pub fn invalidate(&mut self, dimension: u32, (chunk_x, chunk_z): (i32, i32)) {
+        let before = self.entries.len();
+        let mut freed = 0;
+        self.entries.retain(|key, entry| {
+            let stale = key.dimension == dimension
+                && (-1..=1).any(|dx| {
+                    (-1..=1).any(|dz| {
+                        group_of((chunk_x + dx, chunk_z + dz), key.level) == key.chunk_coords
+                    })
+                });
+            if stale {
+                freed += entry.bytes;
+            }
+            !stale
+        });
+        self.used_bytes -= freed;
+
+        if self.entries.len() < before {
+            log::debug!(
+                target: RENDERING,
+                "Dropped {} cached meshes around chunk ({}, {})",
+                before - self.entries.len(),
+                chunk_x,
+                chunk_z
+            );
+        }
+    }
+
+    /// Drop meshes colored by a palette revision older than `revision`
+    ///
+    /// Their keys can no longer be requested, so they would only hold
+    /// memory until evicted.
+    pub fn invalidate_palettes(&mut self, revision: u32) {
+        let mut freed = 0;
+        self.entries.retain(|key, entry| {
+            let stale = key
+                .palette_version
+                .is_some_and(|(_, mesh_revision)| mesh_revision < revision);
+            if stale {
+                freed += entry.bytes;
+            }
+            !stale
+        });
+        self.used_bytes -= freed;
+    }
+
+    /// Drop least recently used meshes until the cache fits its budget
+    fn evict(&mut self) {
+        let mut evicted = 0;
+        while self.used_bytes > self.budget_bytes {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| *key)
+            else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&oldest) {
+                self.used_bytes -= entry.bytes;
+                evicted += 1;
+            }
+        }
+
+        if evicted > 0 {
+            log::debug!(
+                target: RENDERING,
+                "Evicted {} cached meshes, {} KiB of {} KiB used",
+                evicted,
+                self.used_bytes / 1024,
+                self.budget_bytes / 1024
+            );
+        }
+    }
+}
+
+/// Vertex and index data of a mesh in bytes
+pub fn mesh_bytes(mesh: &Mesh) -> usize {
+    let vertices = mesh.count_vertices() * mesh.get_vertex_size() as usize;
+    let indices = mesh.indices().map_or(0, |indices| match indices {
+        Indices::U16(indices) => indices.len() * 2,
+        Indices::U32(indices) => indices.len() * 4,
+    });
+    vertices + indices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(chunk_coords: (i32, i32), level: u8, palette_version: Option<(usize, u32)>) -> MeshKey {
+        MeshKey {
+            dimension: 1,
+            chunk_coords,
+            level,
+            view_mode: ViewMode::Map2d,
+            palette_version,
+            overlays: (false, false),
+        }
+    }
+
+    fn cached(chunk_coords: (i32, i32)) -> CachedMesh {
+        CachedMesh {
+            mesh: Handle::default(),
+            material: None,
+            contours: None,
+            chunk_mesh: ChunkMesh {
+                chunk_coords,
+                hex_count: 0,
+                bounds: Rect::default(),
+            },
+        }
+    }
+
+    fn insert(cache: &mut MeshCache, key: MeshKey, bytes: usize) {
+        cache.insert(key, cached(key.chunk_coords), bytes);
+    }
+
+    #[test]
+    fn evicts_least_recently_inserted_first() {
+        let mut cache = MeshCache::new(300);
+        let (a, b, c, d) = (
+            key((0, 0), 0, None),
+            key((1, 0), 0, None),
+            key((2, 0), 0, None),
+            key((3, 0), 0, None),
+        );
+        insert(&mut cache, a, 100);
+        insert(&mut cache, b, 100);
+        insert(&mut cache, c, 100);
+        assert_eq!(cache.used_bytes, 300);
+
+        insert(&mut cache, d, 150);
+        assert!(cache.get(&a).is_none());
+        assert!(cache.get(&b).is_none());
+        assert!(cache.get(&c).is_some());
+        assert!(cache.get(&d).is_some());
+        assert_eq!(cache.used_bytes, 250);
+    }
+
+    #[test]
+    fn get_and_reinsert_refresh_recency() {
+        let mut cache = MeshCache::new(300);
+        let (a, b, c, d) = (
+            key((0, 0), 0, None),
+            key((1, 0), 0, None),
+            key((2, 0), 0, None),
+            key((3, 0), 0, None),
+        );
+        insert(&mut cache, a, 100);
+        insert(&mut cache, b, 100);
+        insert(&mut cache, c, 100);
+
+        // Touching a leaves b as the oldest
+        assert!(cache.get(&a).is_some());
+        insert(&mut cache, d, 100);
+        assert!(cache.get(&b).is_none());
+
+        // Re-inserting c replaces its bytes and makes it the newest
+        insert(&mut cache, c, 50);
+        assert_eq!(cache.used_bytes, 250);
+        insert(&mut cache, b, 100);
+        assert!(cache.get(&a).is_none());
+        assert!(cache.get(&c).is_some());
+        assert_eq!(cache.used_bytes, 250);
+    }
+
+    #[test]
+    fn oversized_mesh_is_not_kept() {
+        let mut cache = MeshCache::new(100);
+        insert(&mut cache, key((0, 0), 0, None), 150);
+        assert!(cache.entries.is_empty());
+        assert_eq!(cache.used_bytes, 0);
+    }
+
+    #[test]
+    fn invalidates_chunk_neighbours_and_superchunks() {
+        let mut cache = MeshCache::new(usize::MAX);
+        let keys = [
+            key((-1, 0), 0, None),
+            key((1, 1), 0, None),
+            key((2, 0), 0, None),
+            key((-1, -1), 1, None),
+            key((0, 0), 1, None),
+            key((1, 0), 1, None),
+        ];
+        for key in keys {
+            insert(&mut cache, key, 10);
+        }
+        let mut other_dimension = key((0, 0), 0, None);
+        other_dimension.dimension = 2;
+        insert(&mut cache, other_dimension, 10);
+
+        // At level 1, chunk (0, 0) and its neighbours fall into the
+        // superchunks from (-1, -1) to (0, 0)
+        cache.invalidate(1, (0, 0));
+        let kept: Vec<_> = keys.iter().filter(|key| cache.get(key).is_some()).collect();
+        assert_eq!(kept, [&keys[2], &keys[5]]);
+        assert!(cache.get(&other_dimension).is_some());
+        assert_eq!(cache.used_bytes, 30);
+    }
+
+    #[test]
+    fn invalidates_older_palette_revisions() {
+        let mut cache = MeshCache::new(usize::MAX);
+        let old = key((0, 0), 0, Some((0, 1)));
+        let other_theme = key((1, 0), 0, Some((1, 1)));
+        let current = key((2, 0), 0, Some((0, 2)));
+        let instanced = key((3, 0), 0, None);
+        for key in [old, other_theme, current, instanced] {
+            insert(&mut cache, key, 10);
+        }
+
+        cache.invalidate_palettes(2);
+        assert!(cache.get(&old).is_none());
+        assert!(cache.get(&other_theme).is_none());
+        assert!(cache.get(&current).is_some());
+        assert!(cache.get(&instanced).is_some());
+        assert_eq!(cache.used_bytes, 20);
+    }
+}
@@ -0,0 +1,391 @@
+use bevy::{
+    ecs::system::EntityCommands,
+    prelude::*,
+    render::storage::ShaderStorageBuffer,
+    sprite::AlphaMode2d,
+    tasks::{AsyncComputeTaskPool, Task, block_on, futures_lite::future},
+};
+use std::sync::Arc;
+
+use crate::{
+    log_targets::RENDERING,
+    terrain::{
+        chunk_mesh::{ChunkMesh, HexTemplate, create_chunk_mesh, create_column_mesh},
+        contours::{CONTOUR_LAYER_Z, ContourLayer, Contours, create_contour_mesh},
+        hillshade::{Heightfield, Hillshade},
+        instanced::{
+            GpuCell, HexInstanceMaterial, HexInstancing, InstancedCells, Renderer,
+            create_instance_cells,
+        },
+        lod::Superchunk,
+        mesh_cache::{CachedMesh, MeshCache, MeshKey, mesh_bytes},
+        palette::BiomePalette,
+        view_mode::ViewMode,
+        world_data::ChunkSlot,
+    },
+};
+
+/// Most chunk meshes handed to the renderer in one frame
+pub const MAX_MESH_UPLOADS_PER_FRAME: usize = 8;
+
+/// Color of the tile shown while a chunk is meshed
+pub const PLACEHOLDER_COLOR: Color = Color::srgb(0.2, 0.2, 0.22);
+
+/// Terrain a chunk mesh is built from
+///
+/// Chunks are passed as slots and decoded by the task, so reading region
+/// files stays off the main thread.
+pub enum MeshSource {
+    /// A single chunk, with the chunks around it when shading or contours
+    /// are on
+    Chunk {
+        chunk: ChunkSlot,
+        neighbors: Option<Vec<((i32, i32), ChunkSlot)>>,
+    },
+    /// The loaded chunks of a superchunk, down-sampled by the task
+    Superchunk {
+        level: u8,
+        members: Vec<((i32, i32), ChunkSlot)>,
+    },
+}
+
+/// Everything needed to mesh one chunk entity
+pub struct MeshJob {
+    pub source: MeshSource,
+    pub chunk_coords: (i32, i32),
+    pub template: Arc<HexTemplate>,
+    /// Centre offset in the mesh's own units, scaled down for superchunks
+    pub center_offset: Vec2,
+    pub palette: Arc<BiomePalette>,
+    pub hillshade: Hillshade,
+    pub contours: Contours,
+    pub view_mode: ViewMode,
+    pub renderer: Renderer,
+}
+
+/// How a finished chunk is drawn
+pub enum ChunkGeometry {
+    /// A mesh with its colors baked in
+    Mesh(Mesh),
+    /// Cell buffer contents for the instanced renderer
+    Instances(InstancedCells),
+}
+
+/// Meshes built by a finished job
+pub struct MeshedChunk {
+    pub geometry: ChunkGeometry,
+    pub chunk_mesh: ChunkMesh,
+    pub contours: Option<Mesh>,
+    pub view_mode: ViewMode,
+}
+
+/// Chunk mesh still being built, the entity shows a placeholder tile until
+/// it finishes
+///
+/// Despawning the entity drops the task, which cancels it.
+#[derive(Component)]
+pub struct PendingChunkMesh {
+    /// `None` when the chunk could not be decoded
+    task: Task<Option<MeshedChunk>>,
+    /// Where the finished meshes are cached
+    key: MeshKey,
+}
+
+/// Materials shared by every chunk entity, the colors are in the meshes
+#[derive(Resource)]
+pub struct ChunkMaterials {
+    /// White, so vertex colors show through
+    pub terrain: Handle<ColorMaterial>,
+    pub contours: Handle<ColorMaterial>,
+    pub columns: Handle<StandardMaterial>,
+    pub placeholder: Handle<ColorMaterial>,
+    pub placeholder_3d: Handle<StandardMaterial>,
+}
+
+impl FromWorld for ChunkMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.resource_mut::<Assets<ColorMaterial>>();
+        let terrain = materials.add(ColorMaterial::from(Color::WHITE));
+        let contours = materials.add(ColorMaterial {
+            alpha_mode: AlphaMode2d::Blend,
+            ..default()
+        });
+        let placeholder = materials.add(ColorMaterial::from(PLACEHOLDER_COLOR));
+
+        let mut standard_materials = world.resource_mut::<Assets<StandardMaterial>>();
+        let columns = standard_materials.add(StandardMaterial {
+            perceptual_roughness: 0.9,
+            ..default()
+        });
+        let placeholder_3d = standard_materials.add(StandardMaterial::from(PLACEHOLDER_COLOR));
+
+        Self {
+            terrain,
+            contours,
+            columns,
+            placeholder,
+            placeholder_3d,
+        }
+    }
+}
+
+impl MeshJob {
+    /// Decode the chunk and build its meshes, shading and contours are 2D only
+    ///
+    /// `None` when a single chunk fails to decode.
+    pub fn run(self) -> Option<MeshedChunk> {
+        let MeshJob {
+            source,
+            chunk_coords,
+            template,
+            center_offset,
+            palette,
+            hillshade,
+            contours,
+            view_mode,
+            renderer,
+        } = self;
+
+        let (cells, chunk, neighbors) = match source {
+            MeshSource::Chunk { chunk, neighbors } => {
+                let chunk = chunk.load()?.into_owned();
+                (chunk.cells(), Some(chunk), neighbors)
+            }
+            MeshSource::Superchunk { level, members } => {
+                let members = Superchunk::decode_members(&members);
+                let cells = Superchunk::build(level, chunk_coords, &members)
+                    .map(|superchunk| superchunk.cells())
+                    .unwrap_or_default();
+                (cells, None, None)
+            }
+        };
+        let heights = chunk
+            .as_ref()
+            .zip(neighbors.as_deref())
+            .map(|(chunk, neighbors)| Heightfield::around(chunk, neighbors));
+
+        if view_mode == ViewMode::Terrain3d {
+            let (mesh, chunk_mesh) = create_column_mesh(
+                &cells,
+                chunk_coords,
+                template.layout(),
+                center_offset,
+                &palette,
+            );
+            return Some(MeshedChunk {
+                geometry: ChunkGeometry::Mesh(mesh),
+                chunk_mesh,
+                contours: None,
+                view_mode,
+            });
+        }
+
+        // Instanced chunks are shaded and get contours whether or not those
+        // are shown, toggling them does not rebuild the chunk
+        if renderer == Renderer::Instanced {
+            let (instances, chunk_mesh) = create_instance_cells(
+                &cells,
+                chunk_coords,
+                template.layout(),
+                center_offset,
+                heights.as_ref().map(|heights| (&hillshade, heights)),
+            );
+            let contour_mesh = chunk
+                .as_ref()
+                .zip(heights.as_ref())
+                .and_then(|(chunk, heights)| {
+                    create_contour_mesh(chunk, heights, template.layout(), center_offset, &contours)
+                });
+            return Some(MeshedChunk {
+                geometry: ChunkGeometry::Instances(instances),
+                chunk_mesh,
+                contours: contour_mesh,
+                view_mode,
+            });
+        }
+
+        let (mesh, chunk_mesh) = create_chunk_mesh(
+            &cells,
+            chunk_coords,
+            &template,
+            center_offset,
+            &palette,
+            heights
+                .as_ref()
+                .filter(|_| hillshade.enabled)
+                .map(|heights| (&hillshade, heights)),
+        );
+        let contour_mesh = chunk
+            .as_ref()
+            .zip(heights.as_ref())
+            .filter(|_| contours.enabled)
+            .and_then(|(chunk, heights)| {
+                create_contour_mesh(chunk, heights, template.layout(), center_offset, &contours)
+            });
+
+        Some(MeshedChunk {
+            geometry: ChunkGeometry::Mesh(mesh),
+            chunk_mesh,
+            contours: contour_mesh,
+            view_mode,
+        })
+    }
+
+    /// Start meshing on the async compute pool, the result is cached
+    /// under `key`
+    pub fn spawn(self, key: MeshKey) -> PendingChunkMesh {
+        PendingChunkMesh {
+            task: AsyncComputeTaskPool::get().spawn(async move { self.run() }),
+            key,
+        }
+    }
+}
+
+/// Flat tile covering a chunk's bounds until its mesh is ready
+///
+/// `scale` is the entity's scale, the tile is built in the entity's units.
+pub fn placeholder_mesh(bounds: Rect, scale: f32) -> Mesh {
+    Rectangle::from_size(bounds.size() / scale)
+        .mesh()
+        .build()
+        .translated_by((bounds.center() / scale).extend(0.0))
+}
+
+/// Give a chunk entity its meshes, replacing its placeholder tile
+///
+/// The contour layer starts hidden when `contours_visible` is false.
+pub fn insert_chunk_mesh(
+    chunk_entity: &mut EntityCommands,
+    cached: &CachedMesh,
+    view_mode: ViewMode,
+    materials: &ChunkMaterials,
+    contours_visible: bool,
+) {
+    chunk_entity.insert(cached.chunk_mesh.clone());
+
+    match view_mode {
+        ViewMode::Map2d => {
+            chunk_entity.insert(Mesh2d(cached.mesh.clone()));
+            match &cached.material {
+                Some(material) => {
+                    chunk_entity
+                        .remove::<MeshMaterial2d<ColorMaterial>>()
+                        .insert(MeshMaterial2d(material.clone()));
+                }
+                None => {
+                    chunk_entity.insert(MeshMaterial2d(materials.terrain.clone()));
+                }
+            }
+
+            // Contours draw above the terrain and despawn with their chunk
+            if let Some(contours) = &cached.contours {
+                chunk_entity.with_child((
+                    Mesh2d(contours.clone()),
+                    MeshMaterial2d(materials.contours.clone()),
+                    Transform::from_xyz(0.0, 0.0, CONTOUR_LAYER_Z),
+                    if contours_visible {
+                        Visibility::Inherited
+                    } else {
+                        Visibility::Hidden
+                    },
+                    ContourLayer,
+                ));
+            }
+        }
+        ViewMode::Terrain3d => {
+            chunk_entity.insert((
+                Mesh3d(cached.mesh.clone()),
+                MeshMaterial3d(materials.columns.clone()),
+            ));
+        }
+    }
+}
+
+/// System swapping finished chunk meshes in for their placeholder tiles
+///
+/// At most `MAX_MESH_UPLOADS_PER_FRAME` meshes are added per frame, the rest
+/// wait for the next frames. Finished meshes are also kept in the cache.
+#[allow(clippy::too_many_arguments)]
+pub fn finish_chunk_meshes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    materials: Res<ChunkMaterials>,
+    mut instancing: ResMut<HexInstancing>,
+    mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
+    mut instance_materials: ResMut<Assets<HexInstanceMaterial>>,
+    hillshade: Res<Hillshade>,
+    contours: Res<Contours>,
+    mut mesh_cache: ResMut<MeshCache>,
+    mut pending_query: Query<(Entity, &mut PendingChunkMesh)>,
+) {
+    let mut uploaded = 0;
+    let mut waiting = 0;
+
+    for (entity, mut pending) in pending_query.iter_mut() {
+        if uploaded >= MAX_MESH_UPLOADS_PER_FRAME {
+            waiting += 1;
+            continue;
+        }
+        let Some(meshed) = block_on(future::poll_once(&mut pending.task)) else {
+            waiting += 1;
+            continue;
+        };
+        // The decode error is already logged, keep the placeholder tile
+        let Some(meshed) = meshed else {
+            commands.entity(entity).remove::<PendingChunkMesh>();
+            continue;
+        };
+
+        let contour_bytes = meshed.contours.as_ref().map_or(0, mesh_bytes);
+        let contour_mesh = meshed.contours.map(|contours| meshes.add(contours));
+        let (cached, bytes) = match meshed.geometry {
+            ChunkGeometry::Mesh(mesh) => {
+                let bytes = mesh_bytes(&mesh) + contour_bytes;
+                let cached = CachedMesh {
+                    mesh: meshes.add(mesh),
+                    material: None,
+                    contours: contour_mesh,
+                    chunk_mesh: meshed.chunk_mesh,
+                };
+                (cached, bytes)
+            }
+            ChunkGeometry::Instances(mut instances) => {
+                instances.fit(instancing.cells_per_chunk());
+                for (cell, &biome) in instances.cells.iter_mut().zip(&instances.biomes) {
+                    cell.biome = instancing.biome_index(biome);
+                }
+                let bytes = instances.cells.len() * size_of::<GpuCell>() + contour_bytes;
+                let cells = buffers.add(ShaderStorageBuffer::from(instances.cells));
+                let cached = CachedMesh {
+                    mesh: instancing.mesh.clone(),
+                    material: Some(instance_materials.add(instancing.material(cells, &hillshade))),
+                    contours: contour_mesh,
+                    chunk_mesh: meshed.chunk_mesh,
+                };
+                (cached, bytes)
+            }
+        };
+
+        let mut chunk_entity = commands.entity(entity);
+        chunk_entity.remove::<PendingChunkMesh>();
+        insert_chunk_mesh(
+            &mut chunk_entity,
+            &cached,
+            meshed.view_mode,
+            &materials,
+            contours.enabled,
+        );
+        mesh_cache.insert(pending.key, cached, bytes);
+
+        uploaded += 1;
+    }
+
+    if uploaded > 0 {
+        log::debug!(
+            target: RENDERING,
+            "Uploaded {} chunk meshes, {} still pending",
+            uploaded,
+            waiting
+        );
+    }
+}
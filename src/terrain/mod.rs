@@ -3,7 +3,26 @@ pub mod biome_colors_range;
 pub mod camera_culling;
 pub mod cell;
 pub mod chunk;
+pub mod chunk_kind;
 pub mod chunk_mesh;
 pub mod color_utils;
+pub mod contours;
+pub mod coords;
+pub mod dimension_switch;
 pub mod dynamic_chunks;
+pub mod hillshade;
+pub mod instanced;
+pub mod live_feed;
+pub mod load_report;
+pub mod loading;
+pub mod lod;
+pub mod mesh_cache;
+pub mod mesh_tasks;
+pub mod module_bindings;
+pub mod palette;
+pub mod region_file;
+pub mod validation;
+pub mod view_mode;
+pub mod water;
+pub mod world_config;
 pub mod world_data;
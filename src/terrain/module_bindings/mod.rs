@@ -0,0 +1,436 @@
+//! Client bindings for the subset of the BitCraft module the viewer reads
+//!
+//! Laid out like the output of `spacetime generate --lang rust` 1.2, trimmed
+//! to the `terrain_chunk_state` table. `spacetimedb-sdk` is pinned to 1.2 in
+//! `Cargo.toml`, re-check the `DbContext` impls against the SDK's trait when
+//! bumping it. The module's reducers are never called,
+//! so `Reducer` has no variants.
+
+#![allow(unused, clippy::all)]
+use spacetimedb_sdk::__codegen::{self as __sdk, __lib, __sats, __ws};
+
+pub mod terrain_chunk_state_table;
+
+pub use terrain_chunk_state_table::*;
+
+/// One of the reducers defined by this module, none are bound
+#[derive(Clone, PartialEq, Debug)]
+pub enum Reducer {}
+
+impl __sdk::InModule for Reducer {
+    type Module = RemoteModule;
+}
+
+impl __sdk::Reducer for Reducer {
+    fn reducer_name(&self) -> &'static str {
+        match *self {}
+    }
+}
+
+impl TryFrom<__ws::ReducerCallInfo<__ws::BsatnFormat>> for Reducer {
+    type Error = __sdk::Error;
+    fn try_from(value: __ws::ReducerCallInfo<__ws::BsatnFormat>) -> __sdk::Result<Self> {
+        Err(
+            __sdk::InternalError::unknown_name("reducer", &value.reducer_name, "ReducerCallInfo")
+                .into(),
+        )
+    }
+}
+
+#[derive(Default)]
+#[allow(non_snake_case)]
+#[doc(hidden)]
+pub struct DbUpdate {
+    terrain_chunk_state: __sdk::TableUpdate<TerrainChunkState>,
+}
+
+impl TryFrom<__ws::DatabaseUpdate<__ws::BsatnFormat>> for DbUpdate {
+    type Error = __sdk::Error;
+    fn try_from(raw: __ws::DatabaseUpdate<__ws::BsatnFormat>) -> Result<Self, Self::Error> {
+        let mut db_update = DbUpdate::default();
+        for table_update in raw.tables {
+            match &table_update.table_name[..] {
+                "terrain_chunk_state" => db_update
+                    .terrain_chunk_state
+                    .append(terrain_chunk_state_table::parse_table_update(table_update)?),
+
+                unknown => {
+                    return Err(__sdk::InternalError::unknown_name(
+                        "table",
+                        unknown,
+                        "DatabaseUpdate",
+                    )
+                    .into());
+                }
+            }
+        }
+        Ok(db_update)
+    }
+}
+
+impl __sdk::InModule for DbUpdate {
+    type Module = RemoteModule;
+}
+
+impl __sdk::DbUpdate for DbUpdate {
+    fn apply_to_client_cache(
+        &self,
+        cache: &mut __sdk::ClientCache<RemoteModule>,
+    ) -> AppliedDiff<'_> {
+        let mut diff = AppliedDiff::default();
+
+        diff.terrain_chunk_state = cache
+            .apply_diff_to_table::<TerrainChunkState>(
+                "terrain_chunk_state",
+                &self.terrain_chunk_state,
+            )
+            .with_updates_by_pk(|row| &row.chunk_index);
+
+        diff
+    }
+}
+
+#[derive(Default)]
+#[allow(non_snake_case)]
+#[doc(hidden)]
+pub struct AppliedDiff<'r> {
+    terrain_chunk_state: __sdk::TableAppliedDiff<'r, TerrainChunkState>,
+}
+
+impl __sdk::InModule for AppliedDiff<'_> {
+    type Module = RemoteModule;
+}
+
+impl<'r> __sdk::AppliedDiff<'r> for AppliedDiff<'r> {
+    fn invoke_row_callbacks(
+        &self,
+        event: &EventContext,
+        callbacks: &mut __sdk::DbCallbacks<RemoteModule>,
+    ) {
+        callbacks.invoke_table_row_callbacks::<TerrainChunkState>(
+            "terrain_chunk_state",
+            &self.terrain_chunk_state,
+            event,
+        );
+    }
+}
+
+#[doc(hidden)]
+pub struct RemoteModule;
+
+impl __sdk::InModule for RemoteModule {
+    type Module = Self;
+}
+
+/// The `reducers` field of [`EventContext`] and [`DbConnection`]
+pub struct RemoteReducers {
+    imp: __sdk::DbContextImpl<RemoteModule>,
+}
+
+impl __sdk::InModule for RemoteReducers {
+    type Module = RemoteModule;
+}
+
+#[doc(hidden)]
+/// The `set_reducer_flags` field of [`DbConnection`]
+pub struct SetReducerFlags {
+    imp: __sdk::DbContextImpl<RemoteModule>,
+}
+
+impl __sdk::InModule for SetReducerFlags {
+    type Module = RemoteModule;
+}
+
+/// The `db` field of [`EventContext`] and [`DbConnection`],
+/// with methods provided by extension traits for each bound table
+pub struct RemoteTables {
+    imp: __sdk::DbContextImpl<RemoteModule>,
+}
+
+impl __sdk::InModule for RemoteTables {
+    type Module = RemoteModule;
+}
+
+/// A connection to the remote module, including a materialized view of the
+/// subscribed rows
+pub struct DbConnection {
+    /// Access to tables via extension traits implemented for [`RemoteTables`]
+    pub db: RemoteTables,
+    pub reducers: RemoteReducers,
+    #[doc(hidden)]
+    pub set_reducer_flags: SetReducerFlags,
+
+    imp: __sdk::DbContextImpl<RemoteModule>,
+}
+
+impl __sdk::InModule for DbConnection {
+    type Module = RemoteModule;
+}
+
+impl __sdk::DbContext for DbConnection {
+    type DbView = RemoteTables;
+    type Reducers = RemoteReducers;
+    type SetReducerFlags = SetReducerFlags;
+
+    fn db(&self) -> &Self::DbView {
+        &self.db
+    }
+    fn reducers(&self) -> &Self::Reducers {
+        &self.reducers
+    }
+    fn set_reducer_flags(&self) -> &Self::SetReducerFlags {
+        &self.set_reducer_flags
+    }
+
+    fn is_active(&self) -> bool {
+        self.imp.is_active()
+    }
+
+    fn disconnect(&self) -> __sdk::Result<()> {
+        self.imp.disconnect()
+    }
+
+    type SubscriptionBuilder = __sdk::SubscriptionBuilder<RemoteModule>;
+
+    fn subscription_builder(&self) -> Self::SubscriptionBuilder {
+        __sdk::SubscriptionBuilder::new(&self.imp)
+    }
+
+    fn try_identity(&self) -> Option<__sdk::Identity> {
+        self.imp.try_identity()
+    }
+    fn connection_id(&self) -> __sdk::ConnectionId {
+        self.imp.connection_id()
+    }
+}
+
+impl DbConnection {
+    /// Builder-pattern constructor for a connection to the remote module
+    pub fn builder() -> __sdk::DbConnectionBuilder<RemoteModule> {
+        __sdk::DbConnectionBuilder::new()
+    }
+
+    /// Spawn a thread which processes WebSocket messages as they are received
+    pub fn run_threaded(&self) -> std::thread::JoinHandle<()> {
+        self.imp.run_threaded()
+    }
+}
+
+impl __sdk::DbConnection for DbConnection {
+    fn new(imp: __sdk::DbContextImpl<RemoteModule>) -> Self {
+        Self {
+            db: RemoteTables { imp: imp.clone() },
+            reducers: RemoteReducers { imp: imp.clone() },
+            set_reducer_flags: SetReducerFlags { imp: imp.clone() },
+            imp,
+        }
+    }
+}
+
+/// A handle on a subscribed query
+#[derive(Clone)]
+pub struct SubscriptionHandle {
+    imp: __sdk::SubscriptionHandleImpl<RemoteModule>,
+}
+
+impl __sdk::InModule for SubscriptionHandle {
+    type Module = RemoteModule;
+}
+
+impl __sdk::SubscriptionHandle for SubscriptionHandle {
+    fn new(imp: __sdk::SubscriptionHandleImpl<RemoteModule>) -> Self {
+        Self { imp }
+    }
+
+    fn is_ended(&self) -> bool {
+        self.imp.is_ended()
+    }
+
+    fn is_active(&self) -> bool {
+        self.imp.is_active()
+    }
+
+    fn unsubscribe_then(self, on_end: __sdk::OnEndedCallback<RemoteModule>) -> __sdk::Result<()> {
+        self.imp.unsubscribe_then(Some(on_end))
+    }
+
+    fn unsubscribe(self) -> __sdk::Result<()> {
+        self.imp.unsubscribe_then(None)
+    }
+}
+
+/// Implements [`__sdk::DbContext`] for a context type holding the usual
+/// `db`, `reducers`, `set_reducer_flags` and `imp` fields
+macro_rules! impl_db_context {
+    ($context:ty) => {
+        impl __sdk::InModule for $context {
+            type Module = RemoteModule;
+        }
+
+        impl __sdk::DbContext for $context {
+            type DbView = RemoteTables;
+            type Reducers = RemoteReducers;
+            type SetReducerFlags = SetReducerFlags;
+
+            fn db(&self) -> &Self::DbView {
+                &self.db
+            }
+            fn reducers(&self) -> &Self::Reducers {
+                &self.reducers
+            }
+            fn set_reducer_flags(&self) -> &Self::SetReducerFlags {
+                &self.set_reducer_flags
+            }
+
+            fn is_active(&self) -> bool {
+                self.imp.is_active()
+            }
+
+            fn disconnect(&self) -> __sdk::Result<()> {
+                self.imp.disconnect()
+            }
+
+            type SubscriptionBuilder = __sdk::SubscriptionBuilder<RemoteModule>;
+
+            fn subscription_builder(&self) -> Self::SubscriptionBuilder {
+                __sdk::SubscriptionBuilder::new(&self.imp)
+            }
+
+            fn try_identity(&self) -> Option<__sdk::Identity> {
+                self.imp.try_identity()
+            }
+            fn connection_id(&self) -> __sdk::ConnectionId {
+                self.imp.connection_id()
+            }
+        }
+    };
+}
+
+/// Passed to row callbacks, holding the event that changed the rows
+pub struct EventContext {
+    pub db: RemoteTables,
+    pub reducers: RemoteReducers,
+    pub set_reducer_flags: SetReducerFlags,
+    pub event: __sdk::Event<Reducer>,
+    imp: __sdk::DbContextImpl<RemoteModule>,
+}
+
+impl __sdk::AbstractEventContext for EventContext {
+    type Event = __sdk::Event<Reducer>;
+    fn event(&self) -> &Self::Event {
+        &self.event
+    }
+    fn new(imp: __sdk::DbContextImpl<RemoteModule>, event: Self::Event) -> Self {
+        Self {
+            db: RemoteTables { imp: imp.clone() },
+            reducers: RemoteReducers { imp: imp.clone() },
+            set_reducer_flags: SetReducerFlags { imp: imp.clone() },
+            event,
+            imp,
+        }
+    }
+}
+
+impl_db_context!(EventContext);
+impl __sdk::EventContext for EventContext {}
+
+/// Passed to reducer callbacks, never constructed as no reducers are bound
+pub struct ReducerEventContext {
+    pub db: RemoteTables,
+    pub reducers: RemoteReducers,
+    pub set_reducer_flags: SetReducerFlags,
+    pub event: __sdk::ReducerEvent<Reducer>,
+    imp: __sdk::DbContextImpl<RemoteModule>,
+}
+
+impl __sdk::AbstractEventContext for ReducerEventContext {
+    type Event = __sdk::ReducerEvent<Reducer>;
+    fn event(&self) -> &Self::Event {
+        &self.event
+    }
+    fn new(imp: __sdk::DbContextImpl<RemoteModule>, event: Self::Event) -> Self {
+        Self {
+            db: RemoteTables { imp: imp.clone() },
+            reducers: RemoteReducers { imp: imp.clone() },
+            set_reducer_flags: SetReducerFlags { imp: imp.clone() },
+            event,
+            imp,
+        }
+    }
+}
+
+impl_db_context!(ReducerEventContext);
+impl __sdk::ReducerEventContext for ReducerEventContext {}
+
+/// Passed to subscription `on_applied` and `on_ended` callbacks
+pub struct SubscriptionEventContext {
+    pub db: RemoteTables,
+    pub reducers: RemoteReducers,
+    pub set_reducer_flags: SetReducerFlags,
+    imp: __sdk::DbContextImpl<RemoteModule>,
+}
+
+impl __sdk::AbstractEventContext for SubscriptionEventContext {
+    type Event = ();
+    fn event(&self) -> &Self::Event {
+        &()
+    }
+    fn new(imp: __sdk::DbContextImpl<RemoteModule>, _event: Self::Event) -> Self {
+        Self {
+            db: RemoteTables { imp: imp.clone() },
+            reducers: RemoteReducers { imp: imp.clone() },
+            set_reducer_flags: SetReducerFlags { imp: imp.clone() },
+            imp,
+        }
+    }
+}
+
+impl_db_context!(SubscriptionEventContext);
+impl __sdk::SubscriptionEventContext for SubscriptionEventContext {}
+
+/// Passed to connection and subscription error callbacks
+pub struct ErrorContext {
+    pub db: RemoteTables,
+    pub reducers: RemoteReducers,
+    pub set_reducer_flags: SetReducerFlags,
+    pub event: Option<__sdk::Error>,
+    imp: __sdk::DbContextImpl<RemoteModule>,
+}
+
+impl __sdk::AbstractEventContext for ErrorContext {
+    type Event = Option<__sdk::Error>;
+    fn event(&self) -> &Self::Event {
+        &self.event
+    }
+    fn new(imp: __sdk::DbContextImpl<RemoteModule>, event: Self::Event) -> Self {
+        Self {
+            db: RemoteTables { imp: imp.clone() },
+            reducers: RemoteReducers { imp: imp.clone() },
+            set_reducer_flags: SetReducerFlags { imp: imp.clone() },
+            event,
+            imp,
+        }
+    }
+}
+
+impl_db_context!(ErrorContext);
+impl __sdk::ErrorContext for ErrorContext {}
+
+impl __sdk::SpacetimeModule for RemoteModule {
+    type DbConnection = DbConnection;
+    type EventContext = EventContext;
+    type ReducerEventContext = ReducerEventContext;
+    type SubscriptionEventContext = SubscriptionEventContext;
+    type ErrorContext = ErrorContext;
+    type Reducer = Reducer;
+    type DbView = RemoteTables;
+    type Reducers = RemoteReducers;
+    type SetReducerFlags = SetReducerFlags;
+    type DbUpdate = DbUpdate;
+    type AppliedDiff<'r> = AppliedDiff<'r>;
+    type SubscriptionHandle = SubscriptionHandle;
+
+    fn register_tables(client_cache: &mut __sdk::ClientCache<Self>) {
+        terrain_chunk_state_table::register_table(client_cache);
+    }
+}
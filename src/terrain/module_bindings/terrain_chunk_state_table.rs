@@ -0,0 +1,107 @@
+#![allow(unused, clippy::all)]
+use spacetimedb_sdk::__codegen::{self as __sdk, __lib, __sats, __ws};
+
+pub use crate::terrain::chunk::TerrainChunkState;
+
+impl __sdk::InModule for TerrainChunkState {
+    type Module = super::RemoteModule;
+}
+
+/// Table handle for the table `terrain_chunk_state`
+pub struct TerrainChunkStateTableHandle<'ctx> {
+    imp: __sdk::TableHandle<TerrainChunkState>,
+    ctx: std::marker::PhantomData<&'ctx super::RemoteTables>,
+}
+
+#[allow(non_camel_case_types)]
+/// Extension trait for access to the table `terrain_chunk_state`
+pub trait TerrainChunkStateTableAccess {
+    #[allow(non_snake_case)]
+    fn terrain_chunk_state(&self) -> TerrainChunkStateTableHandle<'_>;
+}
+
+impl TerrainChunkStateTableAccess for super::RemoteTables {
+    fn terrain_chunk_state(&self) -> TerrainChunkStateTableHandle<'_> {
+        TerrainChunkStateTableHandle {
+            imp: self
+                .imp
+                .get_table::<TerrainChunkState>("terrain_chunk_state"),
+            ctx: std::marker::PhantomData,
+        }
+    }
+}
+
+pub struct TerrainChunkStateInsertCallbackId(__sdk::CallbackId);
+pub struct TerrainChunkStateDeleteCallbackId(__sdk::CallbackId);
+
+impl<'ctx> __sdk::Table for TerrainChunkStateTableHandle<'ctx> {
+    type Row = TerrainChunkState;
+    type EventContext = super::EventContext;
+
+    fn count(&self) -> u64 {
+        self.imp.count()
+    }
+    fn iter(&self) -> impl Iterator<Item = TerrainChunkState> + '_ {
+        self.imp.iter()
+    }
+
+    type InsertCallbackId = TerrainChunkStateInsertCallbackId;
+
+    fn on_insert(
+        &self,
+        callback: impl FnMut(&Self::EventContext, &Self::Row) + Send + 'static,
+    ) -> TerrainChunkStateInsertCallbackId {
+        TerrainChunkStateInsertCallbackId(self.imp.on_insert(Box::new(callback)))
+    }
+
+    fn remove_on_insert(&self, callback: TerrainChunkStateInsertCallbackId) {
+        self.imp.remove_on_insert(callback.0)
+    }
+
+    type DeleteCallbackId = TerrainChunkStateDeleteCallbackId;
+
+    fn on_delete(
+        &self,
+        callback: impl FnMut(&Self::EventContext, &Self::Row) + Send + 'static,
+    ) -> TerrainChunkStateDeleteCallbackId {
+        TerrainChunkStateDeleteCallbackId(self.imp.on_delete(Box::new(callback)))
+    }
+
+    fn remove_on_delete(&self, callback: TerrainChunkStateDeleteCallbackId) {
+        self.imp.remove_on_delete(callback.0)
+    }
+}
+
+#[doc(hidden)]
+pub(super) fn register_table(client_cache: &mut __sdk::ClientCache<super::RemoteModule>) {
+    let _table = client_cache.get_or_make_table::<TerrainChunkState>("terrain_chunk_state");
+    _table.add_unique_constraint::<u64>("chunk_index", |row| &row.chunk_index);
+}
+
+pub struct TerrainChunkStateUpdateCallbackId(__sdk::CallbackId);
+
+impl<'ctx> __sdk::TableWithPrimaryKey for TerrainChunkStateTableHandle<'ctx> {
+    type UpdateCallbackId = TerrainChunkStateUpdateCallbackId;
+
+    fn on_update(
+        &self,
+        callback: impl FnMut(&Self::EventContext, &Self::Row, &Self::Row) + Send + 'static,
+    ) -> TerrainChunkStateUpdateCallbackId {
+        TerrainChunkStateUpdateCallbackId(self.imp.on_update(Box::new(callback)))
+    }
+
+    fn remove_on_update(&self, callback: TerrainChunkStateUpdateCallbackId) {
+        self.imp.remove_on_update(callback.0)
+    }
+}
+
+#[doc(hidden)]
+pub(super) fn parse_table_update(
+    raw_updates: __ws::TableUpdate<__ws::BsatnFormat>,
+) -> __sdk::Result<__sdk::TableUpdate<TerrainChunkState>> {
+    __sdk::TableUpdate::parse_table_update(raw_updates).map_err(|e| {
+        __sdk::InternalError::failed_parse("TableUpdate<TerrainChunkState>", "TableUpdate")
+            .with_cause(e)
+            .into()
+    })
+}
@@ -0,0 +1,399 @@
+use bevy::{
+    asset::{AssetLoadFailedEvent, AssetLoader, LoadContext, io::Reader},
+    prelude::*,
+};
+use serde::Deserialize;
+use std::{collections::HashMap, fmt, io};
+
+use crate::{
+    cli::ViewerOptions,
+    log_targets::{LOADING, RENDERING},
+    terrain::{
+        biome::{Biome, BiomeInfo},
+        biome_colors_range::ColorRange,
+        color_utils::WATER_COLOR,
+        dynamic_chunks::{DynamicChunk, SpawnedChunks, despawn_all_chunks},
+        instanced::Renderer,
+        mesh_cache::MeshCache,
+        view_mode::ViewMode,
+        water::WaterBodyType,
+    },
+};
+
+/// A built-in palette that can be switched to at runtime
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Theme {
+    pub name: &'static str,
+    /// Palette asset, relative to the `assets` directory
+    pub path: &'static str,
+}
+
+/// Built-in themes, in the order `T` cycles through them
+pub const THEMES: [Theme; 4] = [
+    Theme {
+        name: "game",
+        path: "palettes/game.palette.json",
+    },
+    Theme {
+        name: "high-contrast",
+        path: "palettes/high-contrast.palette.json",
+    },
+    Theme {
+        name: "deuteranopia",
+        path: "palettes/deuteranopia.palette.json",
+    },
+    Theme {
+        name: "greyscale",
+        path: "palettes/greyscale.palette.json",
+    },
+];
+
+/// Name of the theme used for a palette given with `--palette`
+pub const CUSTOM_THEME: &str = "custom";
+
+/// Biome colors, loaded from a `.palette.json` asset
+///
+/// Ranges are looked up by the decoded biome value. A border cell without an
+/// entry for its blend strength uses the unblended border entry, then the
+/// entry of its own biome, then `fallback`. Water ranges go from shallow to
+/// deep.
+#[derive(Asset, TypePath, Clone, Debug)]
+pub struct BiomePalette {
+    ranges: HashMap<PaletteKey, ColorRange>,
+    fallback: ColorRange,
+    water: ColorRange,
+    water_bodies: HashMap<WaterBodyType, ColorRange>,
+}
+
+/// Decoded biome value a palette range applies to
+type PaletteKey = (Biome, Option<Biome>, u8);
+
+impl BiomePalette {
+    /// Parse the contents of a `.palette.json` file
+    pub fn from_json(bytes: &[u8]) -> Result<Self, PaletteError> {
+        let file: PaletteFile = serde_json::from_slice(bytes).map_err(PaletteError::Json)?;
+        Ok(BiomePalette::from(file))
+    }
+
+    /// Color range for a packed cell biome
+    pub fn color_range(&self, biome: u32) -> ColorRange {
+        let Ok(info) = BiomeInfo::try_from(biome) else {
+            return self.fallback;
+        };
+
+        [
+            (info.primary, info.secondary, info.blend),
+            (info.primary, info.secondary, 0),
+            (info.primary, None, 0),
+        ]
+        .iter()
+        .find_map(|key| self.ranges.get(key))
+        .copied()
+        .unwrap_or(self.fallback)
+    }
+
+    /// Color range of a biome away from any border
+    pub fn biome_range(&self, biome: Biome) -> ColorRange {
+        self.ranges
+            .get(&(biome, None, 0))
+            .copied()
+            .unwrap_or(self.fallback)
+    }
+
+    /// Color range of a water body, `water` for unknown bodies
+    pub fn water_range(&self, body: Option<WaterBodyType>) -> ColorRange {
+        body.and_then(|body| self.water_bodies.get(&body))
+            .copied()
+            .unwrap_or(self.water)
+    }
+}
+
+/// On-disk layout of a palette
+#[derive(Deserialize)]
+struct PaletteFile {
+    fallback: RangeDef,
+    #[serde(default)]
+    water: Option<RangeDef>,
+    #[serde(default)]
+    water_bodies: HashMap<WaterBodyType, RangeDef>,
+    ranges: Vec<RangeEntry>,
+}
+
+/// Colors at both ends of a range
+#[derive(Deserialize)]
+struct RangeDef {
+    start: [u8; 3],
+    end: [u8; 3],
+}
+
+impl From<RangeDef> for ColorRange {
+    fn from(range: RangeDef) -> Self {
+        ColorRange::from((range.start, range.end))
+    }
+}
+
+/// One biome color range, `border` and `blend` narrow it to border cells
+#[derive(Deserialize)]
+struct RangeEntry {
+    biome: Biome,
+    #[serde(default)]
+    border: Option<Biome>,
+    #[serde(default)]
+    blend: u8,
+    #[serde(flatten)]
+    range: RangeDef,
+}
+
+impl From<PaletteFile> for BiomePalette {
+    fn from(file: PaletteFile) -> Self {
+        let mut ranges = HashMap::new();
+        for entry in file.ranges {
+            ranges.insert(
+                (entry.biome, entry.border, entry.blend),
+                ColorRange::from(entry.range),
+            );
+        }
+
+        BiomePalette {
+            ranges,
+            fallback: ColorRange::from(file.fallback),
+            water: file.water.map_or(
+                ColorRange {
+                    start: WATER_COLOR,
+                    end: WATER_COLOR,
+                },
+                ColorRange::from,
+            ),
+            water_bodies: file
+                .water_bodies
+                .into_iter()
+                .map(|(body, range)| (body, ColorRange::from(range)))
+                .collect(),
+        }
+    }
+}
+
+/// Why a palette file could not be loaded
+#[derive(Debug)]
+pub enum PaletteError {
+    Io(io::Error),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for PaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaletteError::Io(e) => write!(f, "could not read palette: {e}"),
+            PaletteError::Json(e) => write!(f, "invalid palette: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for PaletteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PaletteError::Io(e) => Some(e),
+            PaletteError::Json(e) => Some(e),
+        }
+    }
+}
+
+/// Asset loader for `.palette.json` files
+#[derive(Default)]
+pub struct PaletteLoader;
+
+impl AssetLoader for PaletteLoader {
+    type Asset = BiomePalette;
+    type Settings = ();
+    type Error = PaletteError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(PaletteError::Io)?;
+        BiomePalette::from_json(&bytes)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["palette.json"]
+    }
+}
+
+/// Resource holding the loaded themes and which one chunks are colored with
+#[derive(Resource)]
+pub struct ActivePalette {
+    /// Theme names and palettes, in switching order
+    pub themes: Vec<(String, Handle<BiomePalette>)>,
+    pub active: usize,
+    /// Bumped whenever a palette file changes on disk
+    pub revision: u32,
+}
+
+impl ActivePalette {
+    /// Palette of the active theme
+    pub fn handle(&self) -> &Handle<BiomePalette> {
+        &self.themes[self.active].1
+    }
+
+    /// Name of the active theme
+    pub fn name(&self) -> &str {
+        &self.themes[self.active].0
+    }
+
+    /// Identifies the colors chunks are meshed with, changes with theme
+    /// switches and palette edits
+    pub fn version(&self) -> (usize, u32) {
+        (self.active, self.revision)
+    }
+}
+
+/// Start loading every theme, so switching does not wait on disk
+///
+/// A palette given on the command line is added as an extra theme and shown
+/// first, otherwise the theme named with `--theme` is.
+pub fn load_palette(
+    mut commands: Commands,
+    options: Res<ViewerOptions>,
+    asset_server: Res<AssetServer>,
+) {
+    let mut themes: Vec<_> = THEMES
+        .iter()
+        .map(|theme| (theme.name.to_string(), asset_server.load(theme.path)))
+        .collect();
+
+    let active = match &options.palette {
+        Some(path) => {
+            themes.push((CUSTOM_THEME.to_string(), asset_server.load(path.clone())));
+            themes.len() - 1
+        }
+        None => themes
+            .iter()
+            .position(|(name, _)| *name == options.theme)
+            .unwrap_or_default(),
+    };
+
+    let palette = ActivePalette {
+        themes,
+        active,
+        revision: 0,
+    };
+    log::info!(target: LOADING, "Using theme {}", palette.name());
+    commands.insert_resource(palette);
+}
+
+/// System cycling through the themes with `T`
+pub fn switch_theme(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut palette: ResMut<ActivePalette>,
+    renderer: Res<Renderer>,
+    view_mode: Res<ViewMode>,
+    mut spawned_chunks: ResMut<SpawnedChunks>,
+    chunk_query: Query<Entity, With<DynamicChunk>>,
+) {
+    if !keyboard.just_pressed(KeyCode::KeyT) {
+        return;
+    }
+
+    palette.active = (palette.active + 1) % palette.themes.len();
+    if !renderer.recolors_on_gpu(*view_mode) {
+        despawn_all_chunks(&mut commands, &mut spawned_chunks, &chunk_query);
+    }
+
+    log::info!(target: RENDERING, "Switched to theme {}", palette.name());
+}
+
+/// System re-meshing visible chunks when the active palette changes on disk
+#[allow(clippy::too_many_arguments)]
+pub fn refresh_on_palette_change(
+    mut commands: Commands,
+    mut palette_events: EventReader<AssetEvent<BiomePalette>>,
+    mut failed_events: EventReader<AssetLoadFailedEvent<BiomePalette>>,
+    mut palette: ResMut<ActivePalette>,
+    mut mesh_cache: ResMut<MeshCache>,
+    renderer: Res<Renderer>,
+    view_mode: Res<ViewMode>,
+    mut spawned_chunks: ResMut<SpawnedChunks>,
+    chunk_query: Query<Entity, With<DynamicChunk>>,
+) {
+    for failed in failed_events.read() {
+        log::error!(
+            target: LOADING,
+            "Could not load palette {}: {}",
+            failed.path,
+            failed.error
+        );
+    }
+
+    let mut changed = false;
+    for event in palette_events.read() {
+        // Cached meshes of any theme are stale once its palette changes
+        if palette
+            .themes
+            .iter()
+            .any(|(_, handle)| event.is_modified(handle))
+        {
+            palette.revision += 1;
+            mesh_cache.invalidate_palettes(palette.revision);
+        }
+        changed |= event.is_modified(palette.handle());
+    }
+    if !changed {
+        return;
+    }
+
+    if renderer.recolors_on_gpu(*view_mode) {
+        log::info!(target: RENDERING, "Palette changed, recoloring visible chunks");
+        return;
+    }
+
+    // Chunk colors are baked into the meshes, so rebuild all of them
+    despawn_all_chunks(&mut commands, &mut spawned_chunks, &chunk_query);
+
+    log::info!(target: RENDERING, "Palette changed, re-meshing visible chunks");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, path::Path};
+
+    const ASSETS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets");
+
+    #[test]
+    fn shipped_palettes_parse() {
+        let dir = Path::new(ASSETS).join("palettes");
+        let mut parsed = 0;
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            let bytes = fs::read(&path).unwrap();
+            let palette = match BiomePalette::from_json(&bytes) {
+                Ok(palette) => palette,
+                Err(e) => panic!("{}: {e}", path.display()),
+            };
+            // Greyscale shades every biome by elevation alone, through its fallback
+            let greyscale = path.ends_with("greyscale.palette.json");
+            assert_eq!(palette.ranges.is_empty(), greyscale, "{}", path.display());
+            parsed += 1;
+        }
+        assert!(parsed >= THEMES.len());
+    }
+
+    #[test]
+    fn every_theme_has_a_palette_file() {
+        for theme in THEMES {
+            assert!(
+                Path::new(ASSETS).join(theme.path).is_file(),
+                "{}",
+                theme.name
+            );
+        }
+    }
+}
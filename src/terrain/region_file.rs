@@ -0,0 +1,421 @@
+use memmap2::Mmap;
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    log_targets::LOADING,
+    terrain::{
+        chunk::TerrainChunkState,
+        load_report::{FileReport, LoadError},
+        validation::{QuarantinedChunk, side_len, validate_dimension_side, validate_field_lens},
+    },
+};
+
+/// Byte size of one element of each per-cell field, ordered as `CELL_FIELDS`
+const CELL_FIELD_SIZES: [usize; 7] = [4, 4, 2, 2, 1, 1, 2];
+
+/// Location and header of one encoded chunk inside a region file
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ChunkEntry {
+    pub chunk_index: u64,
+    pub chunk_x: i32,
+    pub chunk_z: i32,
+    pub dimension: u32,
+    /// Byte range of the encoded chunk within the file
+    pub range: Range<usize>,
+    /// Entry counts of the per-cell fields, ordered as `CELL_FIELDS`
+    pub field_lens: [usize; 7],
+}
+
+/// A memory-mapped `.bsatn` region file with an index of its chunks
+///
+/// Only chunk headers are read when opening, each chunk is decoded on demand.
+pub struct RegionFile {
+    pub path: PathBuf,
+    pub entries: Vec<ChunkEntry>,
+    mmap: Mmap,
+}
+
+impl RegionFile {
+    /// Map a region file and index the chunks it contains
+    pub fn open(path: &Path) -> Result<Self, LoadError> {
+        let io_error = |source| LoadError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+
+        let file = File::open(path).map_err(io_error)?;
+        // SAFETY: dumps are treated as read-only while the viewer runs, a file
+        // truncated underneath us is outside what we support.
+        let mmap = unsafe { Mmap::map(&file) }.map_err(io_error)?;
+
+        let (entries, end) = index_chunks(&mmap).map_err(|offset| LoadError::Corrupt {
+            path: path.to_path_buf(),
+            offset,
+        })?;
+
+        // Leftover bytes mean the rows have more fields than we know about
+        if end != mmap.len() {
+            return Err(LoadError::SchemaMismatch {
+                path: path.to_path_buf(),
+                trailing_bytes: mmap.len() - end,
+            });
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries,
+            mmap,
+        })
+    }
+
+    /// Size of the mapped file in bytes
+    pub fn byte_len(&self) -> usize {
+        self.mmap.len()
+    }
+
+    /// Decode a single indexed chunk
+    pub fn decode(&self, entry: &ChunkEntry) -> Result<TerrainChunkState, LoadError> {
+        spacetimedb_lib::bsatn::from_slice(&self.mmap[entry.range.clone()]).map_err(|source| {
+            LoadError::Decode {
+                path: self.path.clone(),
+                source,
+            }
+        })
+    }
+
+    /// Paths of all `.bsatn` files in a directory
+    pub fn list_dir(dir_path: &Path) -> Result<Vec<PathBuf>, LoadError> {
+        let file_extension = "bsatn";
+
+        let entries = fs::read_dir(dir_path).map_err(|source| LoadError::Io {
+            path: dir_path.to_path_buf(),
+            source,
+        })?;
+
+        let mut paths = Vec::new();
+        for entry in entries {
+            let entry = match entry {
+                Ok(e) => e,
+                Err(e) => {
+                    log::warn!(target: LOADING, "Error reading directory entry: {e}");
+                    continue;
+                }
+            };
+
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == file_extension) {
+                paths.push(path);
+            }
+        }
+
+        Ok(paths)
+    }
+
+    /// Open and validate a single region file, reporting what happened to it
+    pub fn load(path: PathBuf) -> (FileReport, Option<Self>) {
+        match Self::open(&path) {
+            Ok(mut region) => {
+                let quarantined = region.quarantine_invalid();
+                let report = FileReport {
+                    path,
+                    chunks: region.entries.len(),
+                    quarantined,
+                    error: None,
+                };
+                (report, Some(region))
+            }
+            Err(e) => {
+                log::warn!(target: LOADING, "Failed to index {}: {e}", path.display());
+                let report = FileReport {
+                    path,
+                    chunks: 0,
+                    quarantined: Vec::new(),
+                    error: Some(e),
+                };
+                (report, None)
+            }
+        }
+    }
+
+    /// Drop entries whose field lengths break chunk invariants
+    ///
+    /// The first valid chunk of each dimension sets the side its other chunks
+    /// in this file must have, `WorldData::add_region_file` checks it against
+    /// the files loaded before.
+    fn quarantine_invalid(&mut self) -> Vec<QuarantinedChunk> {
+        let mut quarantined = Vec::new();
+        let mut valid = Vec::with_capacity(self.entries.len());
+        let mut dimension_sides = HashMap::new();
+
+        for entry in std::mem::take(&mut self.entries) {
+            let mut violations = validate_field_lens(entry.field_lens);
+            let expected = dimension_sides.get(&entry.dimension).copied();
+            violations.extend(validate_dimension_side(
+                entry.dimension,
+                entry.field_lens[0],
+                expected,
+            ));
+
+            if violations.is_empty() {
+                if let Some(side) = side_len(entry.field_lens[0]) {
+                    dimension_sides.entry(entry.dimension).or_insert(side);
+                }
+                valid.push(entry);
+                continue;
+            }
+
+            match self.decode(&entry) {
+                Ok(chunk) => quarantined.push(QuarantinedChunk { chunk, violations }),
+                Err(e) => log::warn!(target: LOADING, "Failed to decode quarantined chunk: {e}"),
+            }
+        }
+
+        if !quarantined.is_empty() {
+            log::warn!(
+                target: LOADING,
+                "Quarantined {} invalid chunks from {}",
+                quarantined.len(),
+                self.path.display()
+            );
+        }
+
+        self.entries = valid;
+        quarantined
+    }
+}
+
+/// Walk the BSATN encoding of `Vec<TerrainChunkState>` and record where each chunk lives
+///
+/// Returns the entries and the offset where the encoded list ends, or on
+/// failure the offset at which the data stopped making sense.
+fn index_chunks(bytes: &[u8]) -> Result<(Vec<ChunkEntry>, usize), usize> {
+    let mut reader = ByteReader { bytes, pos: 0 };
+
+    let count = reader.u32()? as usize;
+    let mut entries = Vec::new();
+
+    for _ in 0..count {
+        let start = reader.pos;
+        let chunk_index = reader.u64()?;
+        let chunk_x = reader.u32()? as i32;
+        let chunk_z = reader.u32()? as i32;
+        let dimension = reader.u32()?;
+
+        let mut field_lens = [0; 7];
+        for (len, size) in field_lens.iter_mut().zip(CELL_FIELD_SIZES) {
+            *len = reader.skip_array(size)?;
+        }
+
+        entries.push(ChunkEntry {
+            chunk_index,
+            chunk_x,
+            chunk_z,
+            dimension,
+            range: start..reader.pos,
+            field_lens,
+        });
+    }
+
+    Ok((entries, reader.pos))
+}
+
+/// Minimal little-endian cursor over BSATN bytes
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], usize> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or(self.pos)?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> Result<u32, usize> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Read all 8 bytes at once, so a cut field reports its start
+    fn u64(&mut self) -> Result<u64, usize> {
+        let bytes = self.take(8)?;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    /// Skip a length-prefixed array of fixed-size elements, returning its length
+    fn skip_array(&mut self, element_size: usize) -> Result<usize, usize> {
+        let len = self.u32()? as usize;
+        self.take(len * element_size)?;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::{validation::ChunkViolation, world_data::WorldData};
+    use std::sync::Arc;
+
+    fn chunk(chunk_index: u64, chunk_x: i32, chunk_z: i32, side: usize) -> TerrainChunkState {
+        let cells = side * side;
+        TerrainChunkState {
+            chunk_index,
+            chunk_x,
+            chunk_z,
+            dimension: 1,
+            biomes: vec![3; cells],
+            biome_density: vec![0; cells],
+            elevations: (0..cells as i16).collect(),
+            water_levels: vec![0; cells],
+            water_body_types: vec![0; cells],
+            zoning_types: vec![0; cells],
+            original_elevations: vec![0; cells],
+        }
+    }
+
+    fn encode(chunks: &[TerrainChunkState]) -> Vec<u8> {
+        spacetimedb_lib::bsatn::to_vec(&chunks.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn indexes_valid_chunks() {
+        let chunks = [chunk(7, -1, 2, 4), chunk(8, 0, 2, 3)];
+        let bytes = encode(&chunks);
+
+        let (entries, end) = index_chunks(&bytes).unwrap();
+        assert_eq!(end, bytes.len());
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].chunk_index, 7);
+        assert_eq!((entries[0].chunk_x, entries[0].chunk_z), (-1, 2));
+        assert_eq!(entries[0].field_lens, [16; 7]);
+        assert_eq!(entries[1].field_lens, [9; 7]);
+
+        // Each range holds exactly one encoded chunk
+        for (entry, chunk) in entries.iter().zip(&chunks) {
+            let decoded: TerrainChunkState =
+                spacetimedb_lib::bsatn::from_slice(&bytes[entry.range.clone()]).unwrap();
+            assert_eq!(&decoded, chunk);
+        }
+    }
+
+    #[test]
+    fn indexes_empty_list() {
+        let bytes = encode(&[]);
+        assert_eq!(index_chunks(&bytes), Ok((Vec::new(), 4)));
+    }
+
+    #[test]
+    fn truncated_data_reports_offset() {
+        let bytes = encode(&[chunk(7, 0, 0, 4), chunk(8, 1, 0, 4)]);
+        let (entries, _) = index_chunks(&bytes).unwrap();
+
+        // Cut inside the last field of the second chunk
+        let truncated = &bytes[..bytes.len() - 3];
+        let offset = index_chunks(truncated).unwrap_err();
+        assert!(offset > entries[1].range.start && offset < truncated.len());
+
+        // Cut inside the header of the second chunk
+        let header_cut = &bytes[..entries[1].range.start + 6];
+        assert_eq!(index_chunks(header_cut), Err(entries[1].range.start));
+    }
+
+    #[test]
+    fn cut_u64_reports_field_start() {
+        let bytes = [1, 0, 0, 0, 2, 0, 0, 0, 3];
+        let mut reader = ByteReader {
+            bytes: &bytes,
+            pos: 0,
+        };
+        assert_eq!(reader.u64(), Ok(1 | 2 << 32));
+        assert_eq!(reader.pos, 8);
+
+        let mut reader = ByteReader {
+            bytes: &bytes[..6],
+            pos: 0,
+        };
+        assert_eq!(reader.u64(), Err(0));
+        assert_eq!(reader.pos, 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn loads_files_with_non_utf8_names() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let dir = std::env::temp_dir().join(format!("bittravel-utf8-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(OsStr::from_bytes(b"region-\xff.bsatn"));
+        fs::write(&path, encode(&[chunk(7, 0, 0, 2)])).unwrap();
+
+        let paths = RegionFile::list_dir(&dir).unwrap();
+        let (report, region) = RegionFile::load(paths[0].clone());
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(paths, [path]);
+        assert!(report.error.is_none());
+        assert_eq!(region.unwrap().entries.len(), 1);
+    }
+
+    #[test]
+    fn side_is_checked_across_files() {
+        let dir = std::env::temp_dir().join(format!("bittravel-sides-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let first = dir.join("first.bsatn");
+        let second = dir.join("second.bsatn");
+        fs::write(&first, encode(&[chunk(7, 0, 0, 4)])).unwrap();
+        fs::write(&second, encode(&[chunk(8, 1, 0, 3), chunk(9, 2, 0, 3)])).unwrap();
+
+        let (_, first) = RegionFile::load(first);
+        let (report, second) = RegionFile::load(second);
+        fs::remove_dir_all(&dir).unwrap();
+        // Each file is consistent on its own
+        assert!(report.quarantined.is_empty());
+
+        let mut world_data = WorldData::default();
+        assert!(
+            world_data
+                .add_region_file(Arc::new(first.unwrap()))
+                .is_empty()
+        );
+        let quarantined = world_data.add_region_file(Arc::new(second.unwrap()));
+
+        let chunks: Vec<_> = quarantined.iter().map(|q| q.chunk.clone()).collect();
+        assert_eq!(chunks, [chunk(8, 1, 0, 3), chunk(9, 2, 0, 3)]);
+        assert_eq!(
+            quarantined[0].violations,
+            [ChunkViolation::SideMismatch {
+                dimension: 1,
+                side: 3,
+                expected: 4,
+            }]
+        );
+
+        let dimension = &world_data.dimensions[&1];
+        let coords: Vec<_> = dimension.chunks.keys().copied().collect();
+        assert_eq!(coords, [(0, 0)]);
+        assert!(dimension.interiors.is_empty());
+        assert_eq!(dimension.chunk_side(), Some(4));
+    }
+
+    #[test]
+    fn trailing_bytes_are_left_unread() {
+        let mut bytes = encode(&[chunk(7, 0, 0, 2)]);
+        let len = bytes.len();
+        bytes.extend_from_slice(&[0xAB; 5]);
+
+        let (entries, end) = index_chunks(&bytes).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(end, len);
+    }
+}
@@ -0,0 +1,206 @@
+use std::fmt;
+
+use crate::terrain::chunk::TerrainChunkState;
+
+/// A broken invariant in a decoded terrain chunk
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ChunkViolation {
+    /// The chunk has no cells at all
+    Empty,
+    /// A per-cell field does not have one entry per biome cell
+    LengthMismatch {
+        field: &'static str,
+        len: usize,
+        expected: usize,
+    },
+    /// The cell count cannot form a square chunk
+    NotSquare { len: usize },
+    /// The chunk is not as wide as the other chunks of its dimension
+    SideMismatch {
+        dimension: u32,
+        side: usize,
+        expected: usize,
+    },
+}
+
+impl fmt::Display for ChunkViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkViolation::Empty => write!(f, "chunk has no cells"),
+            ChunkViolation::LengthMismatch {
+                field,
+                len,
+                expected,
+            } => write!(f, "{field} has {len} entries, expected {expected}"),
+            ChunkViolation::NotSquare { len } => {
+                write!(f, "{len} cells is not a perfect square")
+            }
+            ChunkViolation::SideMismatch {
+                dimension,
+                side,
+                expected,
+            } => write!(
+                f,
+                "chunk is {side} cells wide, dimension {dimension} uses {expected}"
+            ),
+        }
+    }
+}
+
+/// A chunk that failed validation and was kept out of the world
+#[derive(Clone, PartialEq, Debug)]
+pub struct QuarantinedChunk {
+    pub chunk: TerrainChunkState,
+    pub violations: Vec<ChunkViolation>,
+}
+
+/// Names of the per-cell fields of a terrain chunk, in encoding order
+pub const CELL_FIELDS: [&str; 7] = [
+    "biomes",
+    "biome_density",
+    "elevations",
+    "water_levels",
+    "water_body_types",
+    "zoning_types",
+    "original_elevations",
+];
+
+/// Number of cells along one side of a chunk, if the cell count is square
+pub fn side_len(cell_count: usize) -> Option<usize> {
+    let side = (cell_count as f64).sqrt().round() as usize;
+    (side * side == cell_count).then_some(side)
+}
+
+/// Check that per-cell field lengths, ordered as `CELL_FIELDS`, describe one square grid
+///
+/// Whether the grid matches the chunk's `dimension` depends on the other
+/// chunks of that dimension, see `validate_dimension_side`.
+pub fn validate_field_lens(lens: [usize; 7]) -> Vec<ChunkViolation> {
+    let mut violations = Vec::new();
+    let expected = lens[0];
+
+    if expected == 0 {
+        violations.push(ChunkViolation::Empty);
+    } else if side_len(expected).is_none() {
+        violations.push(ChunkViolation::NotSquare { len: expected });
+    }
+
+    for (field, len) in CELL_FIELDS.into_iter().zip(lens).skip(1) {
+        if len != expected {
+            violations.push(ChunkViolation::LengthMismatch {
+                field,
+                len,
+                expected,
+            });
+        }
+    }
+
+    violations
+}
+
+/// Check that a square chunk is as wide as the chunks already accepted for its dimension
+///
+/// `dimension` is an id rather than a size, what it fixes is that every
+/// chunk sharing it lays out one grid of equally sized chunks. `expected` is
+/// `None` for the first chunk of a dimension.
+pub fn validate_dimension_side(
+    dimension: u32,
+    cell_count: usize,
+    expected: Option<usize>,
+) -> Option<ChunkViolation> {
+    let side = side_len(cell_count)?;
+    let expected = expected?;
+    (side != expected).then_some(ChunkViolation::SideMismatch {
+        dimension,
+        side,
+        expected,
+    })
+}
+
+impl TerrainChunkState {
+    /// Number of cells along one side of the chunk, if the cell count is square
+    pub fn side_len(&self) -> Option<usize> {
+        side_len(self.biomes.len())
+    }
+
+    /// Lengths of the per-cell fields, ordered as `CELL_FIELDS`
+    pub fn field_lens(&self) -> [usize; 7] {
+        [
+            self.biomes.len(),
+            self.biome_density.len(),
+            self.elevations.len(),
+            self.water_levels.len(),
+            self.water_body_types.len(),
+            self.zoning_types.len(),
+            self.original_elevations.len(),
+        ]
+    }
+
+    /// Check that every per-cell field describes the same square grid
+    ///
+    /// Returns all violations found, an empty list means the chunk is safe to mesh.
+    pub fn validate(&self) -> Vec<ChunkViolation> {
+        validate_field_lens(self.field_lens())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_lens_have_no_violations() {
+        assert!(validate_field_lens([1024; 7]).is_empty());
+    }
+
+    #[test]
+    fn empty_chunk() {
+        assert_eq!(validate_field_lens([0; 7]), [ChunkViolation::Empty]);
+    }
+
+    #[test]
+    fn not_square() {
+        assert_eq!(
+            validate_field_lens([10; 7]),
+            [ChunkViolation::NotSquare { len: 10 }]
+        );
+    }
+
+    #[test]
+    fn length_mismatch() {
+        let mut lens = [16; 7];
+        lens[2] = 15;
+        lens[6] = 17;
+        assert_eq!(
+            validate_field_lens(lens),
+            [
+                ChunkViolation::LengthMismatch {
+                    field: "elevations",
+                    len: 15,
+                    expected: 16,
+                },
+                ChunkViolation::LengthMismatch {
+                    field: "original_elevations",
+                    len: 17,
+                    expected: 16,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn side_mismatch() {
+        assert_eq!(validate_dimension_side(1, 16, None), None);
+        assert_eq!(validate_dimension_side(1, 16, Some(4)), None);
+        assert_eq!(
+            validate_dimension_side(1, 9, Some(4)),
+            Some(ChunkViolation::SideMismatch {
+                dimension: 1,
+                side: 3,
+                expected: 4,
+            })
+        );
+        // Non-square chunks are reported by validate_field_lens instead
+        assert_eq!(validate_dimension_side(1, 10, Some(4)), None);
+    }
+}
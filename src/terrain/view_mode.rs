@@ -0,0 +1,130 @@
+use bevy::{input::mouse::MouseMotion, prelude::*};
+
+use crate::{
+    log_targets::RENDERING,
+    terrain::dynamic_chunks::{DynamicChunk, SpawnedChunks, despawn_all_chunks},
+};
+
+/// World units between the orbit camera and its focus per unit of map zoom
+pub const ORBIT_DISTANCE_PER_ZOOM: f32 = 1200.0;
+
+/// How the map is drawn, switched with `V`
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ViewMode {
+    /// Flat hexes seen from above by the orthographic map camera
+    #[default]
+    Map2d,
+    /// Hex columns seen by the perspective orbit camera
+    Terrain3d,
+}
+
+/// Perspective camera circling the point the map camera looks at
+///
+/// Panning and zooming still move the map camera, so both views stream the
+/// same chunks and switching keeps the place in view.
+#[derive(Component, Clone, Copy, PartialEq, Debug)]
+pub struct OrbitCamera {
+    /// Rotation around the vertical axis, in radians
+    pub yaw: f32,
+    /// Angle above the map plane, in radians
+    pub pitch: f32,
+}
+
+impl Default for OrbitCamera {
+    fn default() -> Self {
+        Self {
+            yaw: 0.0,
+            pitch: 0.8,
+        }
+    }
+}
+
+/// Spawn the 3D camera and the lights of the 3D view
+pub fn setup_orbit_camera(mut commands: Commands, view_mode: Res<ViewMode>) {
+    commands.spawn((
+        Camera3d::default(),
+        Camera {
+            is_active: *view_mode == ViewMode::Terrain3d,
+            ..default()
+        },
+        OrbitCamera::default(),
+        Transform::IDENTITY,
+    ));
+
+    // The map is in the XY plane with elevation along Z
+    commands.spawn((
+        DirectionalLight {
+            illuminance: 8_000.0,
+            ..default()
+        },
+        Transform::from_xyz(-1.0, 1.0, 2.0).looking_at(Vec3::ZERO, Vec3::Z),
+    ));
+    commands.insert_resource(AmbientLight {
+        brightness: 400.0,
+        ..default()
+    });
+}
+
+/// System orbiting the 3D camera with the right mouse button
+#[allow(clippy::type_complexity)]
+pub fn update_orbit_camera(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    mut motion_events: EventReader<MouseMotion>,
+    map_camera_query: Query<(&Transform, &Projection), (With<Camera2d>, Without<OrbitCamera>)>,
+    mut orbit_query: Query<(&mut OrbitCamera, &mut Transform), Without<Camera2d>>,
+) {
+    let rotate_speed = 0.005;
+
+    let mut total_motion = Vec2::ZERO;
+    for motion in motion_events.read() {
+        total_motion += motion.delta;
+    }
+
+    let Ok((map_transform, Projection::Orthographic(ortho))) = map_camera_query.single() else {
+        return;
+    };
+    let focus = map_transform.translation.truncate().extend(0.0);
+    let distance = ortho.scale * ORBIT_DISTANCE_PER_ZOOM;
+
+    for (mut orbit, mut transform) in orbit_query.iter_mut() {
+        if mouse_input.pressed(MouseButton::Right) {
+            orbit.yaw -= total_motion.x * rotate_speed;
+            orbit.pitch = (orbit.pitch + total_motion.y * rotate_speed).clamp(0.15, 1.5);
+        }
+
+        let offset = Vec3::new(
+            orbit.yaw.sin() * orbit.pitch.cos(),
+            -orbit.yaw.cos() * orbit.pitch.cos(),
+            orbit.pitch.sin(),
+        );
+        *transform =
+            Transform::from_translation(focus + offset * distance).looking_at(focus, Vec3::Z);
+    }
+}
+
+/// System switching between the 2D map and the 3D view with `V`
+pub fn toggle_view_mode(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut view_mode: ResMut<ViewMode>,
+    mut camera_query: Query<(&mut Camera, Has<Camera2d>)>,
+    mut spawned_chunks: ResMut<SpawnedChunks>,
+    chunk_query: Query<Entity, With<DynamicChunk>>,
+) {
+    if !keyboard.just_pressed(KeyCode::KeyV) {
+        return;
+    }
+
+    *view_mode = match *view_mode {
+        ViewMode::Map2d => ViewMode::Terrain3d,
+        ViewMode::Terrain3d => ViewMode::Map2d,
+    };
+    for (mut camera, is_map_camera) in camera_query.iter_mut() {
+        camera.is_active = is_map_camera == (*view_mode == ViewMode::Map2d);
+    }
+
+    // Chunks are meshed differently in each mode
+    despawn_all_chunks(&mut commands, &mut spawned_chunks, &chunk_query);
+
+    log::info!(target: RENDERING, "Switched to {:?} view", *view_mode);
+}
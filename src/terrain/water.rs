@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+/// Depth at which water reaches the deep end of its color range
+pub const FULL_DEPTH: f32 = 20.0;
+
+/// Kind of water body a cell belongs to, as stored in `water_body_types`
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Copy, Eq, Hash)]
+pub enum WaterBodyType {
+    Ocean,
+    Lake,
+    River,
+    Swamp,
+}
+
+impl WaterBodyType {
+    /// Water body for a stored id, `None` for dry cells and unknown ids
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(WaterBodyType::Ocean),
+            2 => Some(WaterBodyType::Lake),
+            3 => Some(WaterBodyType::River),
+            4 => Some(WaterBodyType::Swamp),
+            _ => None,
+        }
+    }
+}
+
+/// Water covering a cell
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Water {
+    /// `None` when the body type is not set or not known
+    pub body: Option<WaterBodyType>,
+    /// Water level above the cell's elevation, always positive
+    pub depth: i32,
+}
+
+impl Water {
+    /// Water over a cell, `None` when the cell is dry
+    pub fn at(elevation: i16, water_level: i16, water_body_type: u8) -> Option<Self> {
+        let depth = water_level as i32 - elevation as i32;
+        if depth <= 0 {
+            return None;
+        }
+
+        Some(Water {
+            body: WaterBodyType::from_id(water_body_type),
+            depth,
+        })
+    }
+
+    /// Depth scaled to 0-1, shallow fords near 0 and deep channels at 1
+    pub fn normalized_depth(&self) -> f32 {
+        (self.depth as f32 / FULL_DEPTH).clamp(0.0, 1.0)
+    }
+}
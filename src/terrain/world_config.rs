@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+
+use crate::{
+    cli::ViewerOptions,
+    log_targets::LOADING,
+    terrain::{
+        coords::{HEX_SIZE, HexGrid},
+        world_data::{DimensionData, WorldData},
+    },
+};
+
+/// Layout of the loaded world and how much of it is kept around the view
+///
+/// The chunk size is taken from the chunks of the dimension on screen, the
+/// defaults only apply until the first chunks are in. Other dimensions may
+/// use another chunk size, see `grid_for`.
+#[derive(Resource, Clone, Debug)]
+pub struct WorldConfig {
+    pub grid: HexGrid,
+    /// Chunks kept loaded past each edge of the view, so panning shows no gaps
+    pub chunk_padding: i32,
+    /// Camera movement in world units before the view is re-evaluated
+    pub refresh_distance: f32,
+    /// World units around the view in which chunks still count as visible
+    pub cull_padding: f32,
+}
+
+impl Default for WorldConfig {
+    fn default() -> Self {
+        Self::from_options(&ViewerOptions::default())
+    }
+}
+
+impl WorldConfig {
+    /// Default grid with the margin requested on the command line
+    pub fn from_options(options: &ViewerOptions) -> Self {
+        let mut config = Self {
+            grid: HexGrid::default(),
+            chunk_padding: options.view_margin,
+            refresh_distance: 0.0,
+            cull_padding: 0.0,
+        };
+        config.set_grid(HexGrid::default());
+        config
+    }
+
+    /// Size the grid for the active dimension's chunks, unchanged while
+    /// there are none
+    ///
+    /// Called again whenever the active dimension changes.
+    pub fn detect_grid(&mut self, world_data: &WorldData) {
+        let Some(chunk_side) = world_data.chunk_side() else {
+            return;
+        };
+        self.set_grid(HexGrid::new(chunk_side, HEX_SIZE));
+
+        let chunk_size = self.grid.chunk_step().abs();
+        log::info!(
+            target: LOADING,
+            "World grid: {chunk_side}x{chunk_side} cells per chunk, {:.1}x{:.1} world units",
+            chunk_size.x,
+            chunk_size.y
+        );
+    }
+
+    /// Grid of a dimension, which need not be the one on screen
+    pub fn grid_for(&self, dimension: &DimensionData) -> HexGrid {
+        match dimension.chunk_side() {
+            Some(side) if side as i32 != self.grid.chunk_side() => {
+                HexGrid::new(side as i32, HEX_SIZE)
+            }
+            _ => self.grid.clone(),
+        }
+    }
+
+    fn set_grid(&mut self, grid: HexGrid) {
+        let chunk_size = grid.chunk_step().abs().min_element();
+        // A quarter of a chunk keeps the view from flickering between updates
+        self.refresh_distance = chunk_size / 4.0;
+        self.cull_padding = chunk_size / 8.0;
+        self.grid = grid;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::chunk::TerrainChunkState;
+
+    fn chunk(dimension: u32, chunk_x: i32, side: usize) -> TerrainChunkState {
+        let cells = side * side;
+        TerrainChunkState {
+            chunk_index: dimension as u64 * 100 + chunk_x as u64,
+            chunk_x,
+            chunk_z: 0,
+            dimension,
+            biomes: vec![1; cells],
+            biome_density: vec![0; cells],
+            elevations: vec![0; cells],
+            water_levels: vec![0; cells],
+            water_body_types: vec![0; cells],
+            zoning_types: vec![0; cells],
+            original_elevations: vec![0; cells],
+        }
+    }
+
+    #[test]
+    fn grid_follows_the_active_dimension() {
+        let mut world_data = WorldData::new();
+        world_data.add_region(vec![chunk(1, 0, 4), chunk(1, 1, 4), chunk(2, 0, 2)]);
+        let mut config = WorldConfig::default();
+
+        world_data.active_dimension = 2;
+        config.detect_grid(&world_data);
+        assert_eq!(config.grid.chunk_side(), 2);
+
+        world_data.active_dimension = 1;
+        config.detect_grid(&world_data);
+        assert_eq!(config.grid.chunk_side(), 4);
+
+        // Each dimension is centred on its own grid
+        let other = &world_data.dimensions[&2];
+        assert_eq!(config.grid_for(other).chunk_side(), 2);
+        world_data.finalize(&config);
+        let expected = HexGrid::new(2, HEX_SIZE).chunk_to_world(Vec2::splat(0.5));
+        assert_eq!(world_data.dimensions[&2].center_offset, expected);
+    }
+}
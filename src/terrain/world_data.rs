@@ -1,168 +1,348 @@
-use crate::terrain::chunk::TerrainChunkState;
+use crate::{
+    log_targets::{LOADING, STREAMING},
+    terrain::{
+        chunk::TerrainChunkState,
+        chunk_kind::{ChunkClassifier, ChunkKey},
+        coords::{ChunkCoord, HexGrid},
+        region_file::RegionFile,
+        validation::{QuarantinedChunk, side_len, validate_dimension_side},
+        world_config::WorldConfig,
+    },
+};
 use bevy::prelude::*;
-use std::collections::HashMap;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
 
 /// Global resource containing all loaded terrain data
 #[derive(Resource)]
 pub struct WorldData {
-    /// All chunks indexed by (chunk_x, chunk_z) coordinates
-    pub chunks: HashMap<(i32, i32), TerrainChunkState>,
+    /// Chunks of every loaded dimension, indexed by dimension id
+    pub dimensions: BTreeMap<u32, DimensionData>,
+    /// Dimension currently shown in the viewer
+    pub active_dimension: u32,
+    /// Tells the overworld and instanced spaces apart, empty until `classify`
+    pub classifier: ChunkClassifier,
+}
+
+/// Terrain of a single dimension (the overworld, a cave, an interior, ...)
+pub struct DimensionData {
+    /// Chunks on the dimension's grid indexed by (chunk_x, chunk_z) coordinates
+    pub chunks: HashMap<(i32, i32), ChunkSlot>,
+    /// Chunks off the grid, such as interiors reusing coordinates around the
+    /// origin, indexed by chunk_index
+    pub interiors: BTreeMap<u64, ChunkSlot>,
     /// World bounds for all loaded data
     pub bounds: (i32, i32, i32, i32), // min_x, max_x, min_z, max_z
     /// Center offset for coordinate system
     pub center_offset: Vec2,
 }
 
+/// Where the data of a loaded chunk lives
+///
+/// Slots are cheap to clone, so they are handed to meshing tasks, which
+/// decode them off the main thread.
+#[derive(Clone)]
+pub enum ChunkSlot {
+    /// Chunk held in memory, e.g. received from the live feed
+    Decoded(Arc<TerrainChunkState>),
+    /// Chunk still encoded in a memory-mapped region file
+    Mapped {
+        region: Arc<RegionFile>,
+        entry: usize,
+    },
+}
+
+impl ChunkSlot {
+    /// Borrow the chunk, decoding it from its region file if needed
+    pub fn load(&self) -> Option<Cow<'_, TerrainChunkState>> {
+        match self {
+            ChunkSlot::Decoded(chunk) => Some(Cow::Borrowed(chunk.as_ref())),
+            ChunkSlot::Mapped { region, entry } => match region.decode(&region.entries[*entry]) {
+                Ok(chunk) => Some(Cow::Owned(chunk)),
+                Err(e) => {
+                    log::warn!(target: STREAMING, "Failed to decode chunk: {e}");
+                    None
+                }
+            },
+        }
+    }
+
+    /// Identity and position of the chunk, read from the index for mapped chunks
+    pub fn key(&self) -> ChunkKey {
+        match self {
+            ChunkSlot::Decoded(chunk) => chunk.key(),
+            ChunkSlot::Mapped { region, entry } => region.entries[*entry].key(),
+        }
+    }
+
+    /// Cells along each side of the chunk, read from the index for mapped chunks
+    pub fn side_len(&self) -> Option<usize> {
+        match self {
+            ChunkSlot::Decoded(chunk) => chunk.side_len(),
+            ChunkSlot::Mapped { region, entry } => side_len(region.entries[*entry].field_lens[0]),
+        }
+    }
+}
+
 impl WorldData {
+    /// Empty world, the active dimension is picked once chunks are classified
     pub fn new() -> Self {
         Self {
-            chunks: HashMap::new(),
-            bounds: (i32::MAX, i32::MIN, i32::MAX, i32::MIN),
-            center_offset: Vec2::ZERO,
+            dimensions: BTreeMap::new(),
+            active_dimension: 0,
+            classifier: ChunkClassifier::default(),
         }
     }
 
     /// Add a region of chunks to the world data
     pub fn add_region(&mut self, chunks: Vec<TerrainChunkState>) {
         for chunk in chunks {
-            // Update bounds
-            self.bounds.0 = self.bounds.0.min(chunk.chunk_x);
-            self.bounds.1 = self.bounds.1.max(chunk.chunk_x);
-            self.bounds.2 = self.bounds.2.min(chunk.chunk_z);
-            self.bounds.3 = self.bounds.3.max(chunk.chunk_z);
+            self.insert_chunk(chunk);
+        }
+    }
 
-            // Store chunk
-            self.chunks.insert((chunk.chunk_x, chunk.chunk_z), chunk);
+    /// Add every chunk indexed in a region file without decoding it
+    ///
+    /// Chunks that are not as wide as the chunks already loaded for their
+    /// dimension, from this or an earlier file, are kept out of the grid and
+    /// returned decoded for the load report.
+    pub fn add_region_file(&mut self, region: Arc<RegionFile>) -> Vec<QuarantinedChunk> {
+        let mut quarantined = Vec::new();
+        for (index, entry) in region.entries.iter().enumerate() {
+            let dimension = self.dimensions.entry(entry.dimension).or_default();
+            let expected = dimension.chunk_side();
+            if let Some(violation) =
+                validate_dimension_side(entry.dimension, entry.field_lens[0], expected)
+            {
+                match region.decode(entry) {
+                    Ok(chunk) => quarantined.push(QuarantinedChunk {
+                        chunk,
+                        violations: vec![violation],
+                    }),
+                    Err(e) => {
+                        log::warn!(target: LOADING, "Failed to decode quarantined chunk: {e}")
+                    }
+                }
+                continue;
+            }
+
+            let slot = ChunkSlot::Mapped {
+                region: region.clone(),
+                entry: index,
+            };
+            dimension.insert_slot(slot, &self.classifier);
+        }
+
+        if !quarantined.is_empty() {
+            log::warn!(
+                target: LOADING,
+                "Quarantined {} chunks from {} that do not match their dimension",
+                quarantined.len(),
+                region.path.display()
+            );
         }
+        quarantined
     }
 
-    /// Calculate center offset after all regions are loaded
-    pub fn finalize(&mut self) {
-        if self.bounds.0 != i32::MAX {
-            let center_x = (self.bounds.0 + self.bounds.1) as f32 / 2.0;
-            let center_z = (self.bounds.2 + self.bounds.3) as f32 / 2.0;
+    /// Insert or replace a single chunk in its dimension
+    pub fn insert_chunk(&mut self, chunk: TerrainChunkState) {
+        self.dimensions
+            .entry(chunk.dimension)
+            .or_default()
+            .insert_slot(ChunkSlot::Decoded(Arc::new(chunk)), &self.classifier);
+    }
+
+    /// Remove a chunk, returning its slot if it was loaded
+    pub fn remove_chunk(&mut self, chunk: &TerrainChunkState) -> Option<ChunkSlot> {
+        self.dimensions
+            .get_mut(&chunk.dimension)?
+            .remove_slot(&chunk.key())
+    }
+
+    /// Detect the overworld and index layouts from every loaded chunk, then
+    /// move chunks that are off their dimension's grid into its interiors
+    pub fn classify(&mut self) {
+        let keys: Vec<ChunkKey> = self
+            .dimensions
+            .values()
+            .flat_map(|dimension| {
+                dimension
+                    .chunks
+                    .values()
+                    .chain(dimension.interiors.values())
+            })
+            .map(ChunkSlot::key)
+            .collect();
+        self.classifier = ChunkClassifier::detect(&keys);
 
-            // Convert to world coordinates (assuming 32x32 chunks)
-            self.center_offset = Vec2::new(center_x * 32.0 * 13.0, center_z * 32.0 * 13.0);
+        for dimension in self.dimensions.values_mut() {
+            dimension.sort_slots(&self.classifier);
         }
     }
 
-    /// Get chunk by coordinates
-    pub fn get_chunk(&self, chunk_x: i32, chunk_z: i32) -> Option<&TerrainChunkState> {
-        self.chunks.get(&(chunk_x, chunk_z))
+    /// Calculate center offsets of every dimension after all regions are
+    /// loaded, each on its own grid
+    pub fn finalize(&mut self, config: &WorldConfig) {
+        for dimension in self.dimensions.values_mut() {
+            let grid = config.grid_for(dimension);
+            dimension.finalize(&grid);
+        }
     }
 
-    /// Get chunks within a world coordinate radius
-    pub fn get_chunks_in_radius(&self, center: Vec2, radius: f32) -> Vec<&TerrainChunkState> {
-        let chunk_size = 32.0 * 13.0; // 32 hexes * hex size
+    /// Cells along each side of the active dimension's chunks, or of any
+    /// loaded chunks while it has none, `None` before any are loaded
+    pub fn chunk_side(&self) -> Option<i32> {
+        self.active()
+            .and_then(DimensionData::chunk_side)
+            .or_else(|| self.dimensions.values().find_map(DimensionData::chunk_side))
+            .map(|side| side as i32)
+    }
 
-        // Convert camera position to chunk grid coordinates
-        let camera_chunk_x = ((center.x + self.center_offset.x) / chunk_size).round() as i32;
-        let camera_chunk_z = ((center.y + self.center_offset.y) / chunk_size).round() as i32;
+    /// The dimension currently shown in the viewer, if it has any data
+    pub fn active(&self) -> Option<&DimensionData> {
+        self.dimensions.get(&self.active_dimension)
+    }
 
-        // Calculate chunk radius from world radius
-        let chunk_radius = ((radius / chunk_size) + 0.5) as i32;
+    /// The loaded dimension after (or before, for negative steps) the active one
+    pub fn cycle_dimension(&self, step: i32) -> Option<u32> {
+        let ids: Vec<u32> = self.dimensions.keys().copied().collect();
+        if ids.is_empty() {
+            return None;
+        }
 
-        self.chunks
-            .values()
-            .filter(|chunk| {
-                // Calculate distance in chunk grid coordinates
-                let dx = (chunk.chunk_x - camera_chunk_x).abs();
-                let dz = (chunk.chunk_z - camera_chunk_z).abs();
+        let current = ids
+            .iter()
+            .position(|&id| id == self.active_dimension)
+            .unwrap_or(0) as i32;
+        let next = (current + step).rem_euclid(ids.len() as i32);
+        Some(ids[next as usize])
+    }
+}
 
-                // Use Manhattan distance for better grid coverage
-                dx + dz <= chunk_radius
-            })
-            .collect()
+impl Default for DimensionData {
+    fn default() -> Self {
+        Self {
+            chunks: HashMap::new(),
+            interiors: BTreeMap::new(),
+            bounds: (i32::MAX, i32::MIN, i32::MAX, i32::MIN),
+            center_offset: Vec2::ZERO,
+        }
     }
+}
 
-    /// Get chunks in a rectangular viewport area
-    pub fn get_chunks_in_viewport(
-        &self,
-        center: Vec2,
-        width: f32,
-        height: f32,
-    ) -> Vec<&TerrainChunkState> {
-        let chunk_size = 32.0 * 13.0; // 32 hexes * hex size
-
-        // Camera position is already in world coordinates relative to center
-        // Convert camera world position to absolute world coordinates, then to chunk grid
-        let camera_world_x = center.x + self.center_offset.x;
-        let camera_world_z = center.y + self.center_offset.y;
-
-        // Convert to chunk grid coordinates
-        let camera_chunk_x = (camera_world_x / chunk_size).floor() as i32;
-        let camera_chunk_z = (camera_world_z / chunk_size).floor() as i32;
-
-        // Calculate how many chunks we need to cover the viewport
-        let chunks_wide = (width / chunk_size).ceil() as i32;
-        let chunks_tall = (height / chunk_size).ceil() as i32;
-
-        // Add radius for smooth loading
-        let radius = 3; // chunks around viewport
-        let half_width = chunks_wide / 2 + radius;
-        let half_height = chunks_tall / 2 + radius;
-
-        let min_x = camera_chunk_x - half_width;
-        let max_x = camera_chunk_x + half_width;
-        let min_z = camera_chunk_z - half_height;
-        let max_z = camera_chunk_z + half_height;
-
-        // Debug the coordinate conversion
-        log::info!(
-            "Camera at ({:.1}, {:.1}) -> world ({:.1}, {:.1}) -> chunk ({}, {})",
-            center.x,
-            center.y,
-            camera_world_x,
-            camera_world_z,
-            camera_chunk_x,
-            camera_chunk_z
-        );
-        log::info!(
-            "Viewport {}x{} -> chunks {}x{} + {} radius -> range X=[{}, {}], Z=[{}, {}]",
-            width,
-            height,
-            chunks_wide,
-            chunks_tall,
-            radius,
-            min_x,
-            max_x,
-            min_z,
-            max_z
-        );
-
-        let selected: Vec<_> = self
+impl DimensionData {
+    /// Insert or replace a chunk, growing the bounds if it lands on the grid
+    ///
+    /// Chunks the classifier places off the grid, and chunks whose coordinates
+    /// are already taken by a different chunk_index, go to the interiors.
+    pub fn insert_slot(&mut self, slot: ChunkSlot, classifier: &ChunkClassifier) {
+        let key = slot.key();
+        let taken = self
             .chunks
-            .values()
-            .filter(|chunk| {
-                chunk.chunk_x >= min_x
-                    && chunk.chunk_x <= max_x
-                    && chunk.chunk_z >= min_z
-                    && chunk.chunk_z <= max_z
-            })
+            .get(&key.coords())
+            .is_some_and(|existing| existing.key().chunk_index != key.chunk_index);
+        if taken || !classifier.on_grid(&key) {
+            self.interiors.insert(key.chunk_index, slot);
+            return;
+        }
+
+        // Update bounds
+        let (chunk_x, chunk_z) = key.coords();
+        self.bounds.0 = self.bounds.0.min(chunk_x);
+        self.bounds.1 = self.bounds.1.max(chunk_x);
+        self.bounds.2 = self.bounds.2.min(chunk_z);
+        self.bounds.3 = self.bounds.3.max(chunk_z);
+
+        // Store chunk, replacing an older version of it
+        self.interiors.remove(&key.chunk_index);
+        self.chunks.insert(key.coords(), slot);
+    }
+
+    /// Re-insert every chunk after the classifier changed, grid chunks
+    /// first so no interior takes the place of one
+    fn sort_slots(&mut self, classifier: &ChunkClassifier) {
+        let mut slots: Vec<ChunkSlot> = self
+            .chunks
+            .drain()
+            .map(|(_, slot)| slot)
+            .chain(std::mem::take(&mut self.interiors).into_values())
             .collect();
+        slots.sort_by_key(|slot| {
+            let key = slot.key();
+            (!classifier.on_grid(&key), key.chunk_index)
+        });
 
-        log::info!(
-            "Selected {} chunks from bounds [{}, {}] x [{}, {}]. Available chunks: {}",
-            selected.len(),
-            self.bounds.0,
-            self.bounds.1,
-            self.bounds.2,
-            self.bounds.3,
-            self.chunks.len()
-        );
+        self.bounds = Self::default().bounds;
+        for slot in slots {
+            self.insert_slot(slot, classifier);
+        }
+    }
 
-        // Log some actual chunk coordinates that were selected
-        for (i, chunk) in selected.iter().take(5).enumerate() {
-            log::info!("  Chunk {}: ({}, {})", i, chunk.chunk_x, chunk.chunk_z);
+    /// Cells along each side of this dimension's chunks, `None` while it is empty
+    pub fn chunk_side(&self) -> Option<usize> {
+        self.chunks.values().find_map(ChunkSlot::side_len)
+    }
+
+    /// Remove a chunk from the grid or the interiors, returning its slot if
+    /// it was loaded
+    ///
+    /// Bounds are left as they are so the camera centre stays stable.
+    pub fn remove_slot(&mut self, key: &ChunkKey) -> Option<ChunkSlot> {
+        if let Some(slot) = self.interiors.remove(&key.chunk_index) {
+            return Some(slot);
+        }
+        let on_grid = self
+            .chunks
+            .get(&key.coords())
+            .is_some_and(|slot| slot.key().chunk_index == key.chunk_index);
+        if on_grid {
+            self.chunks.remove(&key.coords())
+        } else {
+            None
+        }
+    }
+
+    /// Camera position showing the middle of this dimension
+    pub fn camera_home(&self) -> Vec2 {
+        -self.center_offset
+    }
+
+    /// Camera position showing the given chunk, matching the streaming viewport
+    pub fn chunk_camera_position(&self, grid: &HexGrid, chunk_x: i32, chunk_z: i32) -> Vec2 {
+        grid.chunk_center(ChunkCoord::new(chunk_x, chunk_z)) - self.center_offset
+    }
+
+    /// Calculate center offset after all regions are loaded
+    pub fn finalize(&mut self, grid: &HexGrid) {
+        if self.bounds.0 != i32::MAX {
+            // Middle of the loaded chunks, past the last one's far edge
+            let center_x = (self.bounds.0 + self.bounds.1 + 1) as f32 / 2.0;
+            let center_z = (self.bounds.2 + self.bounds.3 + 1) as f32 / 2.0;
+
+            self.center_offset = grid.chunk_to_world(Vec2::new(center_x, center_z));
         }
+    }
+
+    /// Get chunk by coordinates, decoding it if it is still mapped
+    pub fn get_chunk(&self, chunk_x: i32, chunk_z: i32) -> Option<Cow<'_, TerrainChunkState>> {
+        self.chunks.get(&(chunk_x, chunk_z))?.load()
+    }
 
-        selected
+    /// Slots of the loaded chunks around a chunk, without decoding them
+    pub fn slots_around(&self, (chunk_x, chunk_z): (i32, i32)) -> Vec<((i32, i32), ChunkSlot)> {
+        (-1..=1)
+            .flat_map(|dx| (-1..=1).map(move |dz| (chunk_x + dx, chunk_z + dz)))
+            .filter(|&coords| coords != (chunk_x, chunk_z))
+            .filter_map(|coords| Some((coords, self.chunks.get(&coords)?.clone())))
+            .collect()
     }
 }
 
-impl FromWorld for WorldData {
-    fn from_world(_world: &mut World) -> Self {
+impl Default for WorldData {
+    fn default() -> Self {
         WorldData::new()
     }
 }